@@ -3,4 +3,6 @@ pub trait Cache {
     fn save(&mut self, id: &str, data: Vec<u8>);
     fn get(&self, id: &str) -> Option<&Vec<u8>>;
     fn delete(&mut self, id: &str);
+    // Removes every entry whose id starts with the prefix
+    fn delete_prefix(&mut self, prefix: &str);
 }
\ No newline at end of file
@@ -32,6 +32,10 @@ impl Cache for MemoryCache {
     fn delete(&mut self, id: &str) {
         self.data.remove(id);
     }
+
+    fn delete_prefix(&mut self, prefix: &str) {
+        self.data.retain(|id, _| !id.starts_with(prefix));
+    }
 }
 
 #[cfg(test)]
@@ -51,5 +55,10 @@ mod tests {
         assert_eq!(cache.get("ID0").unwrap(), &"hello world".as_bytes().to_vec());
         cache.delete("ID0");
         assert_eq!(cache.has("ID0"), false);
+        cache.save("A:ID1", Vec::new());
+        cache.save("B:ID1", Vec::new());
+        cache.delete_prefix("A:");
+        assert!(!cache.has("A:ID1"));
+        assert!(cache.has("B:ID1"));
     }
 }
\ No newline at end of file
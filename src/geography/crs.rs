@@ -0,0 +1,418 @@
+use std::f64::consts::PI;
+
+// Semi-major axis of the sphere of Web Mercator
+const WEB_MERCATOR_RADIUS: f64 = 6378137.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ellipsoid {
+    // Semi-major axis in metres
+    pub a: f64,
+    // Inverse flattening, 0 for a sphere
+    pub inverse_flattening: f64,
+}
+
+#[allow(dead_code)]
+impl Ellipsoid {
+    pub const WGS84: Ellipsoid = Ellipsoid { a: 6378137.0, inverse_flattening: 298.257223563 };
+    // Also used by CGCS2000, which differs from it by less than a tenth of a millimetre
+    pub const GRS80: Ellipsoid = Ellipsoid { a: 6378137.0, inverse_flattening: 298.257222101 };
+
+    pub fn new(a: f64, inverse_flattening: f64) -> Ellipsoid {
+        Ellipsoid { a, inverse_flattening }
+    }
+
+    // Square of the first eccentricity
+    pub fn e2(&self) -> f64 {
+        if self.inverse_flattening == 0.0 {
+            return 0.0;
+        }
+        let f = 1.0 / self.inverse_flattening;
+        f * (2.0 - f)
+    }
+
+    // Distance along the meridian from the equator to a latitude in radians
+    fn meridian_arc(&self, latitude: f64) -> f64 {
+        let e2 = self.e2();
+        let (e4, e6) = (e2 * e2, e2 * e2 * e2);
+        self.a * ((1.0 - e2 / 4.0 - 3.0 * e4 / 64.0 - 5.0 * e6 / 256.0) * latitude
+            - (3.0 * e2 / 8.0 + 3.0 * e4 / 32.0 + 45.0 * e6 / 1024.0) * (2.0 * latitude).sin()
+            + (15.0 * e4 / 256.0 + 45.0 * e6 / 1024.0) * (4.0 * latitude).sin()
+            - (35.0 * e6 / 3072.0) * (6.0 * latitude).sin())
+    }
+}
+
+// Transverse Mercator, used by UTM and Gauss-Kruger, distances are in metres and angles in degrees
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransverseMercator {
+    pub ellipsoid: Ellipsoid,
+    pub central_meridian: f64,
+    pub latitude_of_origin: f64,
+    pub scale_factor: f64,
+    pub false_easting: f64,
+    pub false_northing: f64,
+}
+
+// Coordinate reference system of the coordinates of a source
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Crs {
+    // Longitude/latitude in degrees
+    Geographic,
+    WebMercator,
+    TransverseMercator(TransverseMercator),
+}
+
+#[allow(dead_code)]
+impl TransverseMercator {
+    pub fn new(ellipsoid: Ellipsoid, central_meridian: f64, scale_factor: f64, false_easting: f64, false_northing: f64) -> TransverseMercator {
+        TransverseMercator { ellipsoid, central_meridian, latitude_of_origin: 0.0, scale_factor, false_easting, false_northing }
+    }
+
+    pub fn utm(zone: u32, north: bool) -> TransverseMercator {
+        let false_northing = if north { 0.0 } else { 10000000.0 };
+        TransverseMercator::new(Ellipsoid::WGS84, zone as f64 * 6.0 - 183.0, 0.9996, 500000.0, false_northing)
+    }
+
+    // Zones of 3 or 6 degrees, the false easting is prefixed with the zone number when it is given
+    pub fn gauss_kruger(central_meridian: f64, zone: Option<u32>) -> TransverseMercator {
+        let false_easting = zone.map(|zone| zone as f64 * 1000000.0).unwrap_or(0.0) + 500000.0;
+        TransverseMercator::new(Ellipsoid::GRS80, central_meridian, 1.0, false_easting, 0.0)
+    }
+
+    pub fn project(self, (longitude, latitude): (f64, f64)) -> (f64, f64) {
+        let (a, e2, k0) = (self.ellipsoid.a, self.ellipsoid.e2(), self.scale_factor);
+        let ep2 = e2 / (1.0 - e2);
+        let phi = latitude.to_radians();
+        let (sin, cos, tan) = (phi.sin(), phi.cos(), phi.tan());
+
+        let n = a / (1.0 - e2 * sin * sin).sqrt();
+        let t = tan * tan;
+        let c = ep2 * cos * cos;
+        let d = (longitude - self.central_meridian).to_radians() * cos;
+        let m = self.ellipsoid.meridian_arc(phi) - self.ellipsoid.meridian_arc(self.latitude_of_origin.to_radians());
+
+        let x = k0 * n * (d
+            + (1.0 - t + c) * d.powi(3) / 6.0
+            + (5.0 - 18.0 * t + t * t + 72.0 * c - 58.0 * ep2) * d.powi(5) / 120.0);
+        let y = k0 * (m + n * tan * (d * d / 2.0
+            + (5.0 - t + 9.0 * c + 4.0 * c * c) * d.powi(4) / 24.0
+            + (61.0 - 58.0 * t + t * t + 600.0 * c - 330.0 * ep2) * d.powi(6) / 720.0));
+        (x + self.false_easting, y + self.false_northing)
+    }
+
+    pub fn unproject(self, (x, y): (f64, f64)) -> (f64, f64) {
+        let (a, e2, k0) = (self.ellipsoid.a, self.ellipsoid.e2(), self.scale_factor);
+        let ep2 = e2 / (1.0 - e2);
+
+        // Latitude of the point of the central meridian with the same northing
+        let m = self.ellipsoid.meridian_arc(self.latitude_of_origin.to_radians()) + (y - self.false_northing) / k0;
+        let mu = m / (a * (1.0 - e2 / 4.0 - 3.0 * e2 * e2 / 64.0 - 5.0 * e2.powi(3) / 256.0));
+        let e1 = (1.0 - (1.0 - e2).sqrt()) / (1.0 + (1.0 - e2).sqrt());
+        let phi1 = mu
+            + (3.0 * e1 / 2.0 - 27.0 * e1.powi(3) / 32.0) * (2.0 * mu).sin()
+            + (21.0 * e1 * e1 / 16.0 - 55.0 * e1.powi(4) / 32.0) * (4.0 * mu).sin()
+            + (151.0 * e1.powi(3) / 96.0) * (6.0 * mu).sin()
+            + (1097.0 * e1.powi(4) / 512.0) * (8.0 * mu).sin();
+
+        let (sin, cos, tan) = (phi1.sin(), phi1.cos(), phi1.tan());
+        let c = ep2 * cos * cos;
+        let t = tan * tan;
+        let n = a / (1.0 - e2 * sin * sin).sqrt();
+        let r = a * (1.0 - e2) / (1.0 - e2 * sin * sin).powf(1.5);
+        let d = (x - self.false_easting) / (n * k0);
+
+        let latitude = phi1 - (n * tan / r) * (d * d / 2.0
+            - (5.0 + 3.0 * t + 10.0 * c - 4.0 * c * c - 9.0 * ep2) * d.powi(4) / 24.0
+            + (61.0 + 90.0 * t + 298.0 * c + 45.0 * t * t - 252.0 * ep2 - 3.0 * c * c) * d.powi(6) / 720.0);
+        let longitude = (d
+            - (1.0 + 2.0 * t + c) * d.powi(3) / 6.0
+            + (5.0 - 2.0 * c + 28.0 * t - 3.0 * c * c + 8.0 * ep2 + 24.0 * t * t) * d.powi(5) / 120.0) / cos;
+        (self.central_meridian + longitude.to_degrees(), latitude.to_degrees())
+    }
+}
+
+#[allow(dead_code)]
+impl Crs {
+    pub fn default() -> Crs {
+        Crs::Geographic
+    }
+
+    // An EPSG code: geographic WGS-84 and CGCS2000, Web Mercator, WGS-84 UTM zones and CGCS2000 Gauss-Kruger zones
+    pub fn parse(name: &str) -> Result<Crs, String> {
+        let upper = name.trim().to_uppercase();
+        let code = upper.strip_prefix("EPSG:").unwrap_or(&upper);
+        let code: u32 = code.parse().map_err(|_| format!("unknown coordinate reference system '{}'", name))?;
+        let crs = match code {
+            4326 | 4490 => Crs::Geographic,
+            3857 | 900913 => Crs::WebMercator,
+            32601..=32660 => Crs::TransverseMercator(TransverseMercator::utm(code - 32600, true)),
+            32701..=32760 => Crs::TransverseMercator(TransverseMercator::utm(code - 32700, false)),
+            // 6 degree zones 13 to 23, with and without the zone in the false easting
+            4491..=4501 => Crs::TransverseMercator(TransverseMercator::gauss_kruger((code - 4491) as f64 * 6.0 + 75.0, Some(code - 4491 + 13))),
+            4502..=4512 => Crs::TransverseMercator(TransverseMercator::gauss_kruger((code - 4502) as f64 * 6.0 + 75.0, None)),
+            // 3 degree zones 25 to 45, with and without the zone in the false easting
+            4513..=4533 => Crs::TransverseMercator(TransverseMercator::gauss_kruger((code - 4513) as f64 * 3.0 + 75.0, Some(code - 4513 + 25))),
+            4534..=4554 => Crs::TransverseMercator(TransverseMercator::gauss_kruger((code - 4534) as f64 * 3.0 + 75.0, None)),
+            _ => return Err(format!("unsupported coordinate reference system '{}'", name)),
+        };
+        Ok(crs)
+    }
+
+    // The coordinate reference system of the WKT of a .prj file
+    pub fn from_wkt(text: &str) -> Result<Crs, String> {
+        let root = Wkt::parse(text)?;
+        match root.name.as_str() {
+            "GEOGCS" | "GEOGCRS" => return Ok(Crs::Geographic),
+            "PROJCS" => {},
+            // WKT2 names its projection by CONVERSION/METHOD rather than PROJECTION
+            "PROJCRS" | "PROJECTEDCRS" => return Err(String::from("WKT2 projected CRS is not supported, use a WKT1 .prj or set the CRS of the source")),
+            name => return Err(format!("unsupported WKT '{}'", name)),
+        }
+
+        let title = root.text().unwrap_or("").to_lowercase();
+        let projection = root.child("PROJECTION").and_then(|node| node.text()).unwrap_or("").to_lowercase();
+        let ellipsoid = root.child("GEOGCS")
+            .and_then(|node| node.child("DATUM"))
+            .and_then(|node| node.child("SPHEROID"))
+            .map(|node| Ellipsoid::new(node.number(1).unwrap_or(0.0), node.number(2).unwrap_or(0.0)))
+            .unwrap_or(Ellipsoid::WGS84);
+        let parameter = |name: &str| {
+            root.children("PARAMETER")
+                .find(|node| node.text().map(|text| text.eq_ignore_ascii_case(name)) == Some(true))
+                .and_then(|node| node.number(1))
+        };
+        // Metres per unit of the coordinates and of the false easting/northing
+        let unit = root.child("UNIT").and_then(|node| node.number(1)).unwrap_or(1.0);
+
+        if projection.contains("auxiliary_sphere") || projection.contains("pseudo_mercator")
+            || title.contains("web_mercator") || title.contains("pseudo-mercator")
+            || (projection.starts_with("mercator") && ellipsoid.inverse_flattening == 0.0) {
+            return Ok(Crs::WebMercator);
+        }
+        if projection == "transverse_mercator" || projection == "gauss_kruger" {
+            // The projection scales with the ellipsoid, measuring it in the unit gives coordinates in the unit
+            let ellipsoid = Ellipsoid::new(ellipsoid.a / unit, ellipsoid.inverse_flattening);
+            let mut tm = TransverseMercator::new(
+                ellipsoid,
+                parameter("central_meridian").unwrap_or(0.0),
+                parameter("scale_factor").unwrap_or(1.0),
+                parameter("false_easting").unwrap_or(0.0),
+                parameter("false_northing").unwrap_or(0.0),
+            );
+            tm.latitude_of_origin = parameter("latitude_of_origin").unwrap_or(0.0);
+            return Ok(Crs::TransverseMercator(tm));
+        }
+        Err(format!("unsupported projection '{}'", projection))
+    }
+
+    pub fn unproject(self, xy: (f64, f64)) -> (f64, f64) {
+        match self {
+            Crs::Geographic => xy,
+            Crs::WebMercator => (
+                (xy.0 / WEB_MERCATOR_RADIUS).to_degrees(),
+                (2.0 * (xy.1 / WEB_MERCATOR_RADIUS).exp().atan() - PI / 2.0).to_degrees(),
+            ),
+            Crs::TransverseMercator(tm) => tm.unproject(xy),
+        }
+    }
+
+    pub fn project(self, longitude_latitude: (f64, f64)) -> (f64, f64) {
+        match self {
+            Crs::Geographic => longitude_latitude,
+            Crs::WebMercator => (
+                longitude_latitude.0.to_radians() * WEB_MERCATOR_RADIUS,
+                (PI / 4.0 + longitude_latitude.1.to_radians() / 2.0).tan().ln() * WEB_MERCATOR_RADIUS,
+            ),
+            Crs::TransverseMercator(tm) => tm.project(longitude_latitude),
+        }
+    }
+}
+
+// A node of a WKT, like SPHEROID["WGS 84",6378137,298.257223563]
+struct Wkt {
+    name: String,
+    args: Vec<WktArg>,
+}
+
+enum WktArg {
+    Text(String),
+    Number(f64),
+    Node(Wkt),
+}
+
+impl Wkt {
+    fn parse(text: &str) -> Result<Wkt, String> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut pos = 0;
+        let node = Wkt::parse_node(&chars, &mut pos)?;
+        skip_spaces(&chars, &mut pos);
+        if pos < chars.len() {
+            return Err(format!("unexpected '{}' at {} of WKT", chars[pos], pos));
+        }
+        Ok(node)
+    }
+
+    fn parse_node(chars: &[char], pos: &mut usize) -> Result<Wkt, String> {
+        skip_spaces(chars, pos);
+        let start = *pos;
+        while *pos < chars.len() && (chars[*pos].is_alphanumeric() || chars[*pos] == '_') {
+            *pos += 1;
+        }
+        let name: String = chars[start..*pos].iter().collect::<String>().to_uppercase();
+        if name.is_empty() {
+            return Err(format!("expected a keyword at {} of WKT", start));
+        }
+
+        let mut args = Vec::new();
+        skip_spaces(chars, pos);
+        if *pos >= chars.len() || (chars[*pos] != '[' && chars[*pos] != '(') {
+            // A bare keyword, like EAST in AXIS["Easting",EAST]
+            return Ok(Wkt { name, args });
+        }
+        *pos += 1;
+        loop {
+            skip_spaces(chars, pos);
+            match chars.get(*pos) {
+                Some('"') => {
+                    let start = *pos + 1;
+                    let end = chars[start..].iter().position(|c| *c == '"').ok_or("unterminated string in WKT")? + start;
+                    args.push(WktArg::Text(chars[start..end].iter().collect()));
+                    *pos = end + 1;
+                },
+                Some(c) if c.is_ascii_digit() || *c == '-' || *c == '+' || *c == '.' => {
+                    let start = *pos;
+                    while *pos < chars.len() && (chars[*pos].is_ascii_alphanumeric() || "+-.".contains(chars[*pos])) {
+                        *pos += 1;
+                    }
+                    let number: String = chars[start..*pos].iter().collect();
+                    args.push(WktArg::Number(number.parse().map_err(|_| format!("invalid number '{}' in WKT", number))?));
+                },
+                Some(_) => args.push(WktArg::Node(Wkt::parse_node(chars, pos)?)),
+                None => return Err(String::from("unterminated WKT")),
+            }
+
+            skip_spaces(chars, pos);
+            match chars.get(*pos) {
+                Some(',') => *pos += 1,
+                Some(']') | Some(')') => {
+                    *pos += 1;
+                    return Ok(Wkt { name, args });
+                },
+                _ => return Err(format!("expected ',' or ']' at {} of WKT", pos)),
+            }
+        }
+    }
+
+    // The first quoted argument, the name of most nodes
+    fn text(&self) -> Option<&str> {
+        self.args.iter().find_map(|arg| match arg {
+            WktArg::Text(text) => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
+    // The argument at an index, counting every argument
+    fn number(&self, index: usize) -> Option<f64> {
+        match self.args.get(index) {
+            Some(WktArg::Number(number)) => Some(*number),
+            _ => None,
+        }
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Wkt> + 'a {
+        self.args.iter().filter_map(move |arg| match arg {
+            WktArg::Node(node) if node.name == name => Some(node),
+            _ => None,
+        })
+    }
+
+    fn child<'a>(&'a self, name: &'a str) -> Option<&'a Wkt> {
+        self.children(name).next()
+    }
+}
+
+fn skip_spaces(chars: &[char], pos: &mut usize) {
+    while *pos < chars.len() && chars[*pos].is_whitespace() {
+        *pos += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UTM_50N: &str = r#"PROJCS["WGS_1984_UTM_Zone_50N",GEOGCS["GCS_WGS_1984",DATUM["D_WGS_1984",SPHEROID["WGS_1984",6378137.0,298.257223563]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]],PROJECTION["Transverse_Mercator"],PARAMETER["False_Easting",500000.0],PARAMETER["False_Northing",0.0],PARAMETER["Central_Meridian",117.0],PARAMETER["Scale_Factor",0.9996],PARAMETER["Latitude_Of_Origin",0.0],UNIT["Meter",1.0]]"#;
+    const CGCS2000_GK_38: &str = r#"PROJCS["CGCS2000_3_Degree_GK_Zone_38",GEOGCS["GCS_China_Geodetic_Coordinate_System_2000",DATUM["D_China_2000",SPHEROID["CGCS2000",6378137.0,298.257222101]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]],PROJECTION["Gauss_Kruger"],PARAMETER["False_Easting",38500000.0],PARAMETER["False_Northing",0.0],PARAMETER["Central_Meridian",114.0],PARAMETER["Scale_Factor",1.0],PARAMETER["Latitude_Of_Origin",0.0],UNIT["Meter",1.0]]"#;
+    const WEB_MERCATOR: &str = r#"PROJCS["WGS_1984_Web_Mercator_Auxiliary_Sphere",GEOGCS["GCS_WGS_1984",DATUM["D_WGS_1984",SPHEROID["WGS_1984",6378137.0,298.257223563]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]],PROJECTION["Mercator_Auxiliary_Sphere"],PARAMETER["False_Easting",0.0],PARAMETER["False_Northing",0.0],PARAMETER["Central_Meridian",0.0],PARAMETER["Standard_Parallel_1",0.0],PARAMETER["Auxiliary_Sphere_Type",0.0],UNIT["Meter",1.0]]"#;
+    const GEOGRAPHIC: &str = r#"GEOGCS["GCS_WGS_1984",DATUM["D_WGS_1984",SPHEROID["WGS_1984",6378137.0,298.257223563]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433],AXIS["Lat",NORTH],AXIS["Lon",EAST]]"#;
+
+    // The series of the Transverse Mercator are exact to centimetres within a zone
+    fn assert_round_trip(crs: &Crs, longitude_latitude: (f64, f64)) {
+        let xy = crs.project(longitude_latitude);
+        let back = crs.unproject(xy);
+        assert!((back.0 - longitude_latitude.0).abs() < 1e-6, "{:?} {:?}", back, longitude_latitude);
+        assert!((back.1 - longitude_latitude.1).abs() < 1e-6, "{:?} {:?}", back, longitude_latitude);
+    }
+
+    #[test]
+    fn test_wkt() {
+        assert_eq!(Crs::from_wkt(GEOGRAPHIC), Ok(Crs::Geographic));
+        assert_eq!(Crs::from_wkt(WEB_MERCATOR), Ok(Crs::WebMercator));
+        assert_eq!(Crs::from_wkt(UTM_50N), Ok(Crs::TransverseMercator(TransverseMercator::utm(50, true))));
+        assert_eq!(Crs::from_wkt(CGCS2000_GK_38), Crs::parse("EPSG:4526"));
+
+        let feet = UTM_50N.replace(r#"UNIT["Meter",1.0]]"#, r#"UNIT["Foot",0.3048]]"#).replace("500000.0", "1640419.95");
+        let feet = Crs::from_wkt(&feet).unwrap();
+        let (x, y) = Crs::from_wkt(UTM_50N).unwrap().project((114.40, 30.67));
+        let (x_feet, y_feet) = feet.project((114.40, 30.67));
+        assert!((x_feet * 0.3048 - x).abs() < 0.01 && (y_feet * 0.3048 - y).abs() < 0.01);
+
+        assert!(Crs::from_wkt(r#"PROJCS["Lambert",PROJECTION["Lambert_Conformal_Conic"]]"#).is_err());
+        assert!(Crs::from_wkt(r#"PROJCS["UTM",PROJECTION["Transverse_Mercator""#).is_err());
+        assert!(Crs::from_wkt("").is_err());
+
+        let wkt2 = r#"PROJCRS["WGS 84 / UTM zone 50N",BASEGEOGCRS["WGS 84",DATUM["World Geodetic System 1984",ELLIPSOID["WGS 84",6378137,298.257223563]]],CONVERSION["UTM zone 50N",METHOD["Transverse Mercator"],PARAMETER["Longitude of natural origin",117]]]"#;
+        assert!(Crs::from_wkt(wkt2).unwrap_err().contains("WKT2"));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Crs::parse("EPSG:4326"), Ok(Crs::Geographic));
+        assert_eq!(Crs::parse("3857"), Ok(Crs::WebMercator));
+        assert_eq!(Crs::parse("epsg:32750"), Ok(Crs::TransverseMercator(TransverseMercator::utm(50, false))));
+        assert_eq!(Crs::parse("EPSG:4508"), Ok(Crs::TransverseMercator(TransverseMercator::gauss_kruger(111.0, None))));
+        assert_eq!(Crs::parse("EPSG:4497"), Ok(Crs::TransverseMercator(TransverseMercator::gauss_kruger(111.0, Some(19)))));
+        assert_eq!(Crs::parse("EPSG:4546"), Ok(Crs::TransverseMercator(TransverseMercator::gauss_kruger(111.0, None))));
+        assert!(Crs::parse("EPSG:2000").is_err());
+        assert!(Crs::parse("utm").is_err());
+    }
+
+    #[test]
+    fn test_transverse_mercator() {
+        let utm = Crs::parse("EPSG:32650").unwrap();
+        let (x, y) = utm.project((117.0, 0.0));
+        assert!((x - 500000.0).abs() < 1e-6 && y.abs() < 1e-6);
+        // Wuhan is 2.6 degrees west of the central meridian of the zone, about 250 km
+        let (x, y) = utm.project((114.40, 30.67));
+        assert!((249000.0..252000.0).contains(&x));
+        assert!((3390000.0..3400000.0).contains(&y));
+
+        let gauss_kruger = Crs::from_wkt(CGCS2000_GK_38).unwrap();
+        let (x, _) = gauss_kruger.project((114.40, 30.67));
+        assert!((38538000.0..38539000.0).contains(&x));
+
+        for crs in [utm, gauss_kruger, Crs::parse("EPSG:32750").unwrap()] {
+            for longitude_latitude in [(114.40, 30.67), (116.5, -20.0), (119.9, 55.0), (114.0, 0.0)] {
+                assert_round_trip(&crs, longitude_latitude);
+            }
+        }
+    }
+
+    #[test]
+    fn test_web_mercator() {
+        let crs = Crs::WebMercator;
+        let (x, y) = crs.project((180.0, 0.0));
+        assert!((x - 20037508.342789244).abs() < 1e-6 && y.abs() < 1e-6);
+        assert_round_trip(&crs, (114.40, 30.67));
+        assert_round_trip(&crs, (-73.98, -40.0));
+    }
+}
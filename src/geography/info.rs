@@ -1,7 +1,13 @@
-use crate::geometry::Rectangle;
+use std::collections::HashMap;
+use crate::geometry::{Point, Rectangle};
+use super::Value;
 
 #[derive(Debug, Clone)]
 pub struct Info {
     pub name: String,
     pub rect: Rectangle,
+    // Where the name is drawn, only set on the largest part of a polygon
+    pub anchor: Option<Point>,
+    // Fields of the dbase record, by name
+    pub attrs: HashMap<String, Value>,
 }
\ No newline at end of file
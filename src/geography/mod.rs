@@ -1,5 +1,9 @@
+pub mod crs;
 pub mod info;
 pub mod shape;
+pub mod value;
 
+pub use crs::*;
 pub use info::*;
-pub use shape::*;
\ No newline at end of file
+pub use shape::*;
+pub use value::*;
\ No newline at end of file
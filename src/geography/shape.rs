@@ -1,5 +1,6 @@
 use crate::geometry::{Point, Polygon, Polyline, Rectangle, Shape};
-use super::Info;
+use std::{collections::HashMap, path::Path};
+use super::{Crs, Info, Value};
 
 #[allow(dead_code)]
 pub struct Node{
@@ -9,22 +10,50 @@ pub struct Node{
 
 #[allow(dead_code)]
 pub struct ShapeFile {
-    nodes: Vec<Node>
+    nodes: Vec<Node>,
+    // Coordinate reference system of the file, overriding its .prj when set
+    crs: Option<Crs>,
 }
 
 #[allow(dead_code)]
 impl ShapeFile {
     pub fn new() -> ShapeFile {
-        ShapeFile {nodes:Vec::new()}
+        ShapeFile {nodes:Vec::new(), crs: None}
     }
 
-    pub fn load(&mut self, file: &str) {
-        let mut reader = shapefile::Reader::from_path(file).unwrap();
+    pub fn set_crs(&mut self, crs: Crs) {
+        self.crs = Some(crs);
+    }
+
+    // The coordinate reference system of the .prj next to a shapefile, longitude/latitude without one
+    pub fn prj_crs(file: &str) -> Result<Crs, String> {
+        let prj = Path::new(file).with_extension("prj");
+        match std::fs::read_to_string(&prj) {
+            Ok(wkt) => Crs::from_wkt(&wkt).map_err(|err| format!("{}: {}", prj.display(), err)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Crs::Geographic),
+            Err(err) => Err(format!("{}: {}", prj.display(), err)),
+        }
+    }
+
+    // Coordinates are converted to longitude/latitude as they are loaded
+    pub fn load(&mut self, file: &str) -> Result<(), String> {
+        let crs = match self.crs {
+            Some(crs) => crs,
+            None => ShapeFile::prj_crs(file)?,
+        };
+        let to_point = |x: f64, y: f64| {
+            let (longitude, latitude) = crs.unproject((x, y));
+            Point { x: longitude, y: latitude }
+        };
+
+        let mut reader = shapefile::Reader::from_path(file).map_err(|err| format!("{}: {}", file, err))?;
         for shape_record in reader.iter_shapes_and_records() {
-            let (shape, record) = shape_record.unwrap();
+            let (shape, record) = shape_record.map_err(|err| format!("{}: {}", file, err))?;
             let mut info = Info{
                 name: String::from(""),
                 rect: Rectangle::uninitialized(),
+                anchor: None,
+                attrs: HashMap::new(),
             };
             
             if let Some(name) = record.get("name") {
@@ -35,51 +64,73 @@ impl ShapeFile {
                 }
             }
 
+            for (field, value) in record {
+                info.attrs.insert(field, Value::from(value));
+            }
+
             match shape {
                 shapefile::Shape::Point(node) => {
-                    let point = Point{x: node.x, y: node.y};
+                    let point = to_point(node.x, node.y);
+                    info.rect = Rectangle::new(&point, &point);
                     let shape = Shape::Point(point);
                     self.nodes.push(Node { shape, info });
                 },
 
+                shapefile::Shape::Multipoint(node) => {
+                    for point in node.points() {
+                        let point = to_point(point.x, point.y);
+                        let mut info = info.clone();
+                        info.rect = Rectangle::new(&point, &point);
+                        let shape = Shape::Point(point);
+                        self.nodes.push(Node { shape, info });
+                    }
+                },
+
                 shapefile::Shape::Polyline(node) => {
-                    let bbox = node.bbox();
-                    let min = Point{x: bbox.min.x, y: bbox.min.y};
-                    let max = Point{x: bbox.max.x, y: bbox.max.y};
-                    info.rect  = Rectangle::new(&min, &max);
-                    
+                    let mut polylines = Vec::new();
                     for part in node.parts() {
                         let mut polyline = Polyline::new();
-                        for point in part {
-                            polyline.append(&Point { x: point.x, y: point.y });
+                        let points: Vec<Point> = part.iter().map(|point| to_point(point.x, point.y)).collect();
+                        for point in unwrap_longitudes(points) {
+                            polyline.append(&point);
                         }
+                        polylines.push(polyline);
+                    }
+                    info.rect = bounds(polylines.iter().flat_map(|polyline| polyline.points()));
+
+                    for polyline in polylines {
                         let shape = Shape::Polyline(polyline);
                         self.nodes.push(Node { shape, info: info.clone() });
                     }
                 },
 
                 shapefile::Shape::Polygon(node) => {
-                    let bbox = node.bbox();
-                    let min = Point{x: bbox.min.x, y: bbox.min.y};
-                    let max = Point{x: bbox.max.x, y: bbox.max.y};
-                    info.rect  = Rectangle::new(&min, &max);
-
-                    for ring in node.rings() {
-                        if let shapefile::PolygonRing::Inner(_) = ring {
-                            continue;
-                        }
-                        let mut polygon = Polygon::new();
-                        for point in ring.points() {
-                            polygon.append(&Point { x: point.x, y: point.y });
+                    let polygons = ring_polygons(node.rings().iter().map(|ring| {
+                        let inner = matches!(ring, shapefile::PolygonRing::Inner(_));
+                        (inner, ring.points().iter().map(|point| to_point(point.x, point.y)).collect())
+                    }));
+                    info.rect = bounds(polygons.iter().flat_map(|polygon| polygon.points()));
+
+                    let largest = polygons.iter()
+                        .enumerate()
+                        .max_by(|(_, a), (_, b)| a.area().total_cmp(&b.area()))
+                        .map(|(i, _)| i);
+                    for (i, polygon) in polygons.into_iter().enumerate() {
+                        let mut info = info.clone();
+                        if Some(i) == largest {
+                            let bbox = polygon.bbox();
+                            let precision = bbox.width().max(bbox.height()) / 100.0;
+                            info.anchor = Some(polygon.pole_of_inaccessibility(precision));
                         }
                         let shape = Shape::Polygon(polygon);
-                        self.nodes.push(Node { shape, info: info.clone() });
+                        self.nodes.push(Node { shape, info });
                     }
                 },
 
                 _ => {},
             }
         }
+        Ok(())
     }
 
     pub fn nodes(&self) -> &Vec<Node> {
@@ -87,14 +138,121 @@ impl ShapeFile {
     }
 }
 
+// Makes a line crossing the antimeridian continuous, a jump of more than 180 degrees between two points
+// is taken as a crossing and the longitudes after it are moved a world back, beyond ±180 degrees
+fn unwrap_longitudes(mut points: Vec<Point>) -> Vec<Point> {
+    let mut offset = 0.0;
+    for i in 1..points.len() {
+        let jump = points[i].x + offset - points[i - 1].x;
+        if jump > 180.0 {
+            offset -= 360.0;
+        } else if jump < -180.0 {
+            offset += 360.0;
+        }
+        points[i].x += offset;
+    }
+    points
+}
+
+// Polygons of rings, each inner ring is a hole of the outer ring before it. The longitudes of a
+// hole are unwrapped relative to its outer ring, so both stay on the same side of the antimeridian
+fn ring_polygons(rings: impl Iterator<Item = (bool, Vec<Point>)>) -> Vec<Polygon> {
+    let mut polygons: Vec<Polygon> = Vec::new();
+    for (inner, points) in rings {
+        let mut points = unwrap_longitudes(points);
+        let outer = if inner { polygons.last_mut() } else { None };
+        let origin = outer.as_ref().and_then(|outer| outer.points().first()).map(|point| point.x);
+        if let (Some(origin), Some(first)) = (origin, points.first()) {
+            let offset = ((origin - first.x) / 360.0).round() * 360.0;
+            for point in points.iter_mut() {
+                point.x += offset;
+            }
+        }
+
+        let mut polygon = Polygon::new();
+        for point in points {
+            polygon.append(&point);
+        }
+        match outer {
+            Some(outer) => outer.add_hole(polygon),
+            None => polygons.push(polygon),
+        }
+    }
+    polygons
+}
+
+// The bounding box of points, the bounding box of the projected coordinates of the file is not
+// the one of the longitude/latitude as projections bend the lines
+fn bounds<'a>(points: impl Iterator<Item = &'a Point>) -> Rectangle {
+    let mut min = Point::new(f64::INFINITY, f64::INFINITY);
+    let mut max = Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for point in points {
+        min = Point::new(min.x.min(point.x), min.y.min(point.y));
+        max = Point::new(max.x.max(point.x), max.y.max(point.y));
+    }
+    Rectangle::new(&min, &max)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_unwrap_longitudes() {
+        let points = vec![Point::new(179.0, 10.0), Point::new(-179.0, 11.0), Point::new(-178.0, 12.0), Point::new(179.5, 13.0)];
+        let points = unwrap_longitudes(points);
+        let longitudes: Vec<f64> = points.iter().map(|point| point.x).collect();
+        assert_eq!(longitudes, vec![179.0, 181.0, 182.0, 179.5]);
+
+        let points = vec![Point::new(-179.0, 0.0), Point::new(179.0, 0.0)];
+        assert_eq!(unwrap_longitudes(points)[1].x, -181.0);
+
+        let points = vec![Point::new(114.2, 30.7), Point::new(114.5, 30.4)];
+        assert_eq!(unwrap_longitudes(points.clone()), points);
+    }
+
+    #[test]
+    fn test_ring_polygons() {
+        // An island crossing the antimeridian with a lake east of it
+        let outer = vec![Point::new(178.0, -1.0), Point::new(-178.0, -1.0), Point::new(-178.0, 1.0), Point::new(178.0, 1.0)];
+        let hole = vec![Point::new(-179.5, -0.5), Point::new(-179.5, 0.5), Point::new(-178.5, 0.5), Point::new(-178.5, -0.5)];
+        let polygons = ring_polygons(vec![(false, outer), (true, hole)].into_iter());
+        assert_eq!(polygons.len(), 1);
+        let longitudes: Vec<f64> = polygons[0].holes()[0].points().iter().map(|point| point.x).collect();
+        assert_eq!(longitudes, vec![180.5, 180.5, 181.5, 181.5]);
+        assert!((polygons[0].area() - 7.0).abs() < 1e-9);
+
+        // A hole without an outer ring is kept as a polygon
+        let hole = vec![Point::new(1.0, 1.0), Point::new(1.0, 2.0), Point::new(2.0, 2.0)];
+        assert_eq!(ring_polygons(vec![(true, hole)].into_iter()).len(), 1);
+    }
+
+    #[test]
+    fn test_bounds() {
+        let points = [Point::new(114.2, 30.7), Point::new(114.5, 30.4), Point::new(114.3, 30.9)];
+        let rect = bounds(points.iter());
+        assert_eq!(rect.min, Point::new(114.2, 30.4));
+        assert_eq!(rect.max, Point::new(114.5, 30.9));
+    }
+
+    #[test]
+    fn test_prj_crs() {
+        let dir = std::env::temp_dir().join("tiny-gis-server-prj");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("utm.shp");
+        std::fs::write(dir.join("utm.prj"), r#"PROJCS["WGS_1984_UTM_Zone_50N",GEOGCS["GCS_WGS_1984",DATUM["D_WGS_1984",SPHEROID["WGS_1984",6378137.0,298.257223563]]],PROJECTION["Transverse_Mercator"],PARAMETER["False_Easting",500000.0],PARAMETER["Central_Meridian",117.0],PARAMETER["Scale_Factor",0.9996],UNIT["Meter",1.0]]"#).unwrap();
+        assert_eq!(ShapeFile::prj_crs(&file.to_string_lossy()), Crs::parse("EPSG:32650"));
+        assert_eq!(ShapeFile::prj_crs(&dir.join("none.shp").to_string_lossy()), Ok(Crs::Geographic));
+
+        // A .prj that cannot be read is reported, not taken as longitude/latitude
+        std::fs::create_dir_all(dir.join("unreadable.prj")).unwrap();
+        assert!(ShapeFile::prj_crs(&dir.join("unreadable.shp").to_string_lossy()).is_err());
+    }
+
     #[test]
     fn test_shapefile() {
         let mut shp = ShapeFile::new();
-        shp.load("resource/wuhan/wuhan_road.shp");
+        shp.load("resource/wuhan/wuhan_road.shp").unwrap();
         for node in shp.nodes() {
             if !node.info.name.is_empty() {
                 println!("{}", node.info.name);
@@ -0,0 +1,75 @@
+use std::fmt::Display;
+
+// An attribute of a feature
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+#[allow(dead_code)]
+impl Value {
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Bool(value) => *value,
+            Value::Number(value) => *value != 0.0,
+            Value::String(value) => !value.is_empty(),
+        }
+    }
+
+    // Strings holding a number are read as that number
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(value) => Some(*value),
+            Value::String(value) => value.trim().parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Number(value) => write!(f, "{}", value),
+            Value::String(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl From<shapefile::dbase::FieldValue> for Value {
+    fn from(value: shapefile::dbase::FieldValue) -> Value {
+        use shapefile::dbase::FieldValue;
+        match value {
+            FieldValue::Character(Some(value)) => Value::String(value),
+            FieldValue::Memo(value) => Value::String(value),
+            FieldValue::Numeric(Some(value)) => Value::Number(value),
+            FieldValue::Float(Some(value)) => Value::Number(value as f64),
+            FieldValue::Integer(value) => Value::Number(value as f64),
+            FieldValue::Currency(value) => Value::Number(value),
+            FieldValue::Double(value) => Value::Number(value),
+            FieldValue::Logical(Some(value)) => Value::Bool(value),
+            _ => Value::Null,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value() {
+        assert!(!Value::Null.is_truthy());
+        assert!(Value::Number(1.0).is_truthy());
+        assert!(!Value::String(String::new()).is_truthy());
+        assert_eq!(Value::String(String::from(" 42 ")).as_number(), Some(42.0));
+        assert_eq!(Value::Bool(true).as_number(), None);
+        assert_eq!(Value::Number(3.0).to_string(), "3");
+        assert_eq!(Value::Null.to_string(), "");
+    }
+}
@@ -0,0 +1,141 @@
+use std::f64::consts::PI;
+use super::{BaseType, Point};
+
+// Measurements on the earth of points in longitude/latitude degrees, in metres and square metres
+
+// Mean radius of the earth, for distances on the sphere
+pub const EARTH_RADIUS: BaseType = 6371008.8;
+// Radius of the sphere with the area of the WGS-84 ellipsoid, for areas on the sphere
+pub const EARTH_AUTHALIC_RADIUS: BaseType = 6371007.2;
+const WGS84_A: BaseType = 6378137.0;
+const WGS84_F: BaseType = 1.0 / 298.257223563;
+const VINCENTY_PRECISION: BaseType = 1e-12;
+const VINCENTY_ITERATIONS: usize = 200;
+
+// The difference of two longitudes in radians within [-π, π], the short way around the antimeridian
+fn longitude_delta(from: BaseType, to: BaseType) -> BaseType {
+    let delta = (to - from).to_radians();
+    (delta + PI).rem_euclid(2.0 * PI) - PI
+}
+
+// Great circle distance on the sphere, within 0.5% of the distance on the ellipsoid
+pub fn haversine(a: &Point, b: &Point) -> BaseType {
+    let (phi1, phi2) = (a.y.to_radians(), b.y.to_radians());
+    let d_phi = phi2 - phi1;
+    let d_lambda = longitude_delta(a.x, b.x);
+    let h = (d_phi / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (d_lambda / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * h.sqrt().min(1.0).asin()
+}
+
+// Distance on the WGS-84 ellipsoid to within a millimetre, None for nearly antipodal points where it does not converge
+pub fn vincenty(a: &Point, b: &Point) -> Option<BaseType> {
+    let semi_minor = WGS84_A * (1.0 - WGS84_F);
+    let l = longitude_delta(a.x, b.x);
+    let u1 = ((1.0 - WGS84_F) * a.y.to_radians().tan()).atan();
+    let u2 = ((1.0 - WGS84_F) * b.y.to_radians().tan()).atan();
+    let (sin_u1, cos_u1) = u1.sin_cos();
+    let (sin_u2, cos_u2) = u2.sin_cos();
+
+    let mut lambda = l;
+    for _ in 0..VINCENTY_ITERATIONS {
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+        let sin_sigma = ((cos_u2 * sin_lambda).powi(2) + (cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda).powi(2)).sqrt();
+        if sin_sigma == 0.0 {
+            return Some(0.0);
+        }
+        let cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+        let sigma = sin_sigma.atan2(cos_sigma);
+        let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+        let cos2_alpha = 1.0 - sin_alpha * sin_alpha;
+        // On the equator cos2_alpha is 0 and the term is unused
+        let cos_2sigma_m = if cos2_alpha != 0.0 { cos_sigma - 2.0 * sin_u1 * sin_u2 / cos2_alpha } else { 0.0 };
+        let c = WGS84_F / 16.0 * cos2_alpha * (4.0 + WGS84_F * (4.0 - 3.0 * cos2_alpha));
+        let previous = lambda;
+        lambda = l + (1.0 - c) * WGS84_F * sin_alpha
+            * (sigma + c * sin_sigma * (cos_2sigma_m + c * cos_sigma * (-1.0 + 2.0 * cos_2sigma_m * cos_2sigma_m)));
+
+        if (lambda - previous).abs() < VINCENTY_PRECISION {
+            let u_sq = cos2_alpha * (WGS84_A * WGS84_A - semi_minor * semi_minor) / (semi_minor * semi_minor);
+            let big_a = 1.0 + u_sq / 16384.0 * (4096.0 + u_sq * (-768.0 + u_sq * (320.0 - 175.0 * u_sq)));
+            let big_b = u_sq / 1024.0 * (256.0 + u_sq * (-128.0 + u_sq * (74.0 - 47.0 * u_sq)));
+            let delta_sigma = big_b * sin_sigma * (cos_2sigma_m + big_b / 4.0
+                * (cos_sigma * (-1.0 + 2.0 * cos_2sigma_m * cos_2sigma_m)
+                    - big_b / 6.0 * cos_2sigma_m * (-3.0 + 4.0 * sin_sigma * sin_sigma) * (-3.0 + 4.0 * cos_2sigma_m * cos_2sigma_m)));
+            return Some(semi_minor * big_a * (sigma - delta_sigma));
+        }
+    }
+    None
+}
+
+// Area of a ring on the sphere, whichever its orientation, the ring does not need to be closed
+pub fn spherical_area(points: &[Point]) -> BaseType {
+    if points.len() < 3 {
+        return 0.0;
+    }
+
+    let mut sum = 0.0;
+    for (i, a) in points.iter().enumerate() {
+        let b = &points[(i + 1) % points.len()];
+        sum += longitude_delta(a.x, b.x) * (2.0 + a.y.to_radians().sin() + b.y.to_radians().sin());
+    }
+    (sum * EARTH_AUTHALIC_RADIUS * EARTH_AUTHALIC_RADIUS / 2.0).abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_haversine() {
+        let a = Point::new(0.0, 0.0);
+        let b = Point::new(1.0, 0.0);
+        assert!((haversine(&a, &b) - EARTH_RADIUS * PI / 180.0).abs() < 1e-6);
+        assert_eq!(haversine(&a, &a), 0.0);
+        assert!((haversine(&Point::new(0.0, 90.0), &Point::new(0.0, -90.0)) - EARTH_RADIUS * PI).abs() < 1e-6);
+
+        // Across the antimeridian the short way, whether the longitudes are wrapped or not
+        let a = Point::new(179.5, 0.0);
+        assert!((haversine(&a, &Point::new(-179.5, 0.0)) - haversine(&a, &Point::new(180.5, 0.0))).abs() < 1e-6);
+        assert!(haversine(&a, &Point::new(-179.5, 0.0)) < 112000.0);
+    }
+
+    #[test]
+    fn test_vincenty() {
+        // Flinders Peak to Buninyong, the example of Vincenty's paper
+        let flinders = Point::new(144.42486788889, -37.95103341667);
+        let buninyong = Point::new(143.92649552778, -37.65282113889);
+        assert!((vincenty(&flinders, &buninyong).unwrap() - 54972.271).abs() < 0.01);
+
+        // A degree of the equator is longer than on the mean sphere
+        let equator = vincenty(&Point::new(0.0, 0.0), &Point::new(1.0, 0.0)).unwrap();
+        assert!((equator - 111319.491).abs() < 0.01);
+        assert_eq!(vincenty(&flinders, &flinders), Some(0.0));
+
+        // Wuhan to Beijing, the sphere is close to the ellipsoid
+        let wuhan = Point::new(114.30, 30.59);
+        let beijing = Point::new(116.40, 39.90);
+        let distance = vincenty(&wuhan, &beijing).unwrap();
+        assert!((distance - haversine(&wuhan, &beijing)).abs() / distance < 0.005);
+
+        assert_eq!(vincenty(&Point::new(0.0, 0.0), &Point::new(179.7, 0.5)), None);
+    }
+
+    #[test]
+    fn test_spherical_area() {
+        // A cell of a degree on the equator
+        let mut cell = vec![Point::new(0.0, 0.0), Point::new(1.0, 0.0), Point::new(1.0, 1.0), Point::new(0.0, 1.0)];
+        let expected = EARTH_AUTHALIC_RADIUS * EARTH_AUTHALIC_RADIUS * 1.0f64.to_radians() * 1.0f64.to_radians().sin();
+        assert!((spherical_area(&cell) - expected).abs() / expected < 1e-9);
+
+        cell.reverse();
+        assert!((spherical_area(&cell) - expected).abs() / expected < 1e-9);
+        cell.push(cell[0].clone());
+        assert!((spherical_area(&cell) - expected).abs() / expected < 1e-9);
+
+        // The same cell across the antimeridian
+        let cell = vec![Point::new(179.5, 0.0), Point::new(-179.5, 0.0), Point::new(-179.5, 1.0), Point::new(179.5, 1.0)];
+        assert!((spherical_area(&cell) - expected).abs() / expected < 1e-9);
+
+        assert_eq!(spherical_area(&cell[..2]), 0.0);
+    }
+}
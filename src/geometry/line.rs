@@ -1,4 +1,4 @@
-use super::{BaseType, NAN, EPSILON, Point};
+use super::{BaseType, NAN, EPSILON, Point, Rectangle};
 
 #[allow(dead_code)]
 #[derive(Debug, PartialEq, Clone)]
@@ -58,6 +58,65 @@ impl Line {
         let vec2 = &line.p2 - &line.p1;
         vec1.cross(&vec2).abs() < EPSILON
     }
+
+    pub fn distance(&self, p: &Point) -> BaseType {
+        let vec = &self.p2 - &self.p1;
+        let length = vec.dot(&vec);
+        if length < EPSILON {
+            return self.p1.distance(p);
+        }
+        let t = (p - &self.p1).dot(&vec) / length;
+        self.interpolate(t).distance(p)
+    }
+
+    pub fn interpolate(&self, t: BaseType) -> Point {
+        if t <= 0.0 {
+            self.p1.clone()
+        } else if t >= 1.0 {
+            self.p2.clone()
+        } else {
+            Point {
+                x: self.p1.x + (self.p2.x - self.p1.x) * t,
+                y: self.p1.y + (self.p2.y - self.p1.y) * t,
+            }
+        }
+    }
+
+    // Liang–Barsky
+    pub fn clip(&self, rect: &Rectangle) -> Option<Line> {
+        let delta = &self.p2 - &self.p1;
+        let checks = [
+            (-delta.x, self.p1.x - rect.min.x),
+            (delta.x, rect.max.x - self.p1.x),
+            (-delta.y, self.p1.y - rect.min.y),
+            (delta.y, rect.max.y - self.p1.y),
+        ];
+
+        let mut t0 = 0.0;
+        let mut t1 = 1.0;
+        for (p, q) in checks {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return None;
+                }
+            } else {
+                let t = q / p;
+                if p < 0.0 {
+                    if t > t1 {
+                        return None;
+                    }
+                    t0 = t.max(t0);
+                } else {
+                    if t < t0 {
+                        return None;
+                    }
+                    t1 = t.min(t1);
+                }
+            }
+        }
+
+        Some(Line::new(&self.interpolate(t0), &self.interpolate(t1)))
+    }
 }
 
 #[cfg(test)]
@@ -108,4 +167,40 @@ mod tests {
         assert_eq!(false, l1.is_orthogonal(&l3));
         assert_eq!(false, l2.is_orthogonal(&l3));
     }
+
+    #[test]
+    fn test_distance() {
+        let l = Line::new(&Point::new(0.0, 0.0), &Point::new(4.0, 0.0));
+        assert_eq!(l.distance(&Point::new(2.0, 3.0)), 3.0);
+        assert_eq!(l.distance(&Point::new(-3.0, 4.0)), 5.0);
+        assert_eq!(l.distance(&Point::new(7.0, -4.0)), 5.0);
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let l = Line::new(&Point::new(1.0, 2.0), &Point::new(5.0, 10.0));
+        assert_eq!(l.interpolate(0.0), Point::new(1.0, 2.0));
+        assert_eq!(l.interpolate(0.5), Point::new(3.0, 6.0));
+        assert_eq!(l.interpolate(1.0), Point::new(5.0, 10.0));
+    }
+
+    #[test]
+    fn test_clip() {
+        let rect = Rectangle::new(&Point::new(0.0, 0.0), &Point::new(10.0, 10.0));
+
+        let l = Line::new(&Point::new(2.0, 3.0), &Point::new(4.0, 5.0));
+        assert_eq!(l.clip(&rect), Some(l.clone()));
+
+        let l = Line::new(&Point::new(-5.0, 5.0), &Point::new(15.0, 5.0));
+        assert_eq!(l.clip(&rect), Some(Line::new(&Point::new(0.0, 5.0), &Point::new(10.0, 5.0))));
+
+        let l = Line::new(&Point::new(5.0, 5.0), &Point::new(5.0, 20.0));
+        assert_eq!(l.clip(&rect), Some(Line::new(&Point::new(5.0, 5.0), &Point::new(5.0, 10.0))));
+
+        let l = Line::new(&Point::new(-5.0, 11.0), &Point::new(15.0, 11.0));
+        assert_eq!(l.clip(&rect), None);
+
+        let l = Line::new(&Point::new(-5.0, 4.0), &Point::new(4.0, 15.0));
+        assert_eq!(l.clip(&rect), None);
+    }
 }
\ No newline at end of file
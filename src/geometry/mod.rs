@@ -1,13 +1,17 @@
 mod base;
+mod geodesic;
 mod point;
 mod line;
 mod polyline;
 mod polygon;
 mod rectangle;
+mod simplify;
 
 pub use base::*;
+pub use geodesic::*;
 pub use point::*;
 pub use line::*;
 pub use polyline::*;
 pub use polygon::*;
-pub use rectangle::*;
\ No newline at end of file
+pub use rectangle::*;
+pub use simplify::*;
\ No newline at end of file
@@ -1,5 +1,5 @@
 use std::ops;
-use super::{BaseType, NAN};
+use super::{BaseType, NAN, haversine, vincenty};
 
 #[allow(dead_code)]
 #[derive(Debug, PartialEq, Clone)]
@@ -39,6 +39,17 @@ impl Point {
         let delta = self - p;
         Point::dot(&delta, &delta).powf(0.5)
     }
+
+    // Distance in metres between longitude/latitude points on the sphere
+    pub fn haversine_distance(&self, p: &Point) -> BaseType {
+        haversine(self, p)
+    }
+
+    // Distance in metres between longitude/latitude points on the WGS-84 ellipsoid,
+    // on the sphere for nearly antipodal points
+    pub fn geodesic_distance(&self, p: &Point) -> BaseType {
+        vincenty(self, p).unwrap_or_else(|| haversine(self, p))
+    }
 }
 
 impl ops::Add<&Point> for &Point {
@@ -180,4 +191,15 @@ mod tests {
         let p2 = Point::new(-2.0, 3.0);
         assert_eq!(5.0, p1.distance(&p2))
     }
+
+    #[test]
+    fn test_geodesic_distance() {
+        let p1 = Point::new(0.0, 0.0);
+        let p2 = Point::new(0.0, 1.0);
+        assert!((p1.haversine_distance(&p2) - 111195.08).abs() < 0.01);
+        assert!((p1.geodesic_distance(&p2) - 110574.39).abs() < 0.01);
+
+        let antipode = Point::new(179.7, 0.5);
+        assert_eq!(p1.geodesic_distance(&antipode), p1.haversine_distance(&antipode));
+    }
 }
\ No newline at end of file
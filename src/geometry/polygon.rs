@@ -1,15 +1,18 @@
-use super::Point;
+use std::{cmp::Ordering, collections::BinaryHeap};
+use super::{Line, Point, Rectangle, simplify, spherical_area};
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct Polygon {
-    points: Vec<Point>
+    points: Vec<Point>,
+    // Inner rings cut out of the polygon
+    holes: Vec<Polygon>,
 }
 
 #[allow(dead_code)]
 impl Polygon {
     pub fn new() -> Polygon {
-        Polygon { points: Vec::new() }
+        Polygon { points: Vec::new(), holes: Vec::new() }
     }
 
     pub fn points_count(&self) -> usize {
@@ -28,6 +31,213 @@ impl Polygon {
     pub fn points(&self) -> &Vec<Point> {
         &self.points
     }
+
+    pub fn add_hole(&mut self, hole: Polygon) {
+        self.holes.push(hole);
+    }
+
+    pub fn holes(&self) -> &Vec<Polygon> {
+        &self.holes
+    }
+
+    pub fn simplify(&self, tolerance: f64) -> Polygon {
+        Polygon {
+            points: simplify(&self.points, tolerance),
+            holes: self.holes.iter().map(|hole| hole.simplify(tolerance)).collect(),
+        }
+    }
+
+    pub fn area(&self) -> f64 {
+        self.signed_area().abs() - self.holes.iter().map(|hole| hole.area()).sum::<f64>()
+    }
+
+    // Area in square metres of a polygon of longitude/latitude points on the sphere
+    pub fn geodesic_area(&self) -> f64 {
+        spherical_area(&self.points) - self.holes.iter().map(|hole| hole.geodesic_area()).sum::<f64>()
+    }
+
+    pub fn centroid(&self) -> Point {
+        let area = self.signed_area();
+        if area.abs() < f64::EPSILON {
+            return self.points.first().cloned().unwrap_or(Point::uninitialized());
+        }
+
+        let mut x = 0.0;
+        let mut y = 0.0;
+        for (a, b) in self.edges() {
+            let cross = a.cross(b);
+            x += (a.x + b.x) * cross;
+            y += (a.y + b.y) * cross;
+        }
+        Point { x: x / (6.0 * area), y: y / (6.0 * area) }
+    }
+
+    pub fn contains(&self, p: &Point) -> bool {
+        self.ring_contains(p) && !self.holes.iter().any(|hole| hole.ring_contains(p))
+    }
+
+    fn ring_contains(&self, p: &Point) -> bool {
+        let mut inside = false;
+        for (a, b) in self.edges() {
+            if (a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    // Distance to the border or the nearest hole, negative outside the polygon
+    pub fn distance(&self, p: &Point) -> f64 {
+        let distance = self.edges()
+            .chain(self.holes.iter().flat_map(|hole| hole.edges()))
+            .map(|(a, b)| Line::new(a, b).distance(p))
+            .fold(f64::INFINITY, f64::min);
+        if self.contains(p) { distance } else { -distance }
+    }
+
+    pub fn bbox(&self) -> Rectangle {
+        let mut min = Point { x: f64::INFINITY, y: f64::INFINITY };
+        let mut max = Point { x: f64::NEG_INFINITY, y: f64::NEG_INFINITY };
+        for point in &self.points {
+            min.x = min.x.min(point.x);
+            min.y = min.y.min(point.y);
+            max.x = max.x.max(point.x);
+            max.y = max.y.max(point.y);
+        }
+        Rectangle { min, max }
+    }
+
+    // The interior point farthest from the border, found to within precision (polylabel)
+    pub fn pole_of_inaccessibility(&self, precision: f64) -> Point {
+        let bbox = self.bbox();
+        let cell_size = bbox.width().min(bbox.height());
+        if self.points.is_empty() || cell_size <= 0.0 {
+            return self.points.first().cloned().unwrap_or(Point::uninitialized());
+        }
+
+        let mut queue = BinaryHeap::new();
+        let half = cell_size / 2.0;
+        let mut x = bbox.min.x;
+        while x < bbox.max.x {
+            let mut y = bbox.min.y;
+            while y < bbox.max.y {
+                queue.push(Cell::new(Point { x: x + half, y: y + half }, half, self));
+                y += cell_size;
+            }
+            x += cell_size;
+        }
+
+        let mut best = Cell::new(self.centroid(), 0.0, self);
+        let center = Cell::new(bbox.center(), 0.0, self);
+        if center.distance > best.distance {
+            best = center;
+        }
+
+        while let Some(cell) = queue.pop() {
+            if cell.distance > best.distance {
+                best = cell.clone();
+            }
+
+            if cell.max - best.distance <= precision {
+                continue;
+            }
+
+            let half = cell.half / 2.0;
+            for (dx, dy) in [(-half, -half), (half, -half), (-half, half), (half, half)] {
+                let center = Point { x: cell.center.x + dx, y: cell.center.y + dy };
+                queue.push(Cell::new(center, half, self));
+            }
+        }
+
+        best.center
+    }
+
+    // Sutherland–Hodgman
+    pub fn clip(&self, rect: &Rectangle) -> Polygon {
+        let points = Polygon::clip_edge(&self.points, |p| p.x >= rect.min.x, |a, b| Polygon::intersect_x(a, b, rect.min.x));
+        let points = Polygon::clip_edge(&points, |p| p.x <= rect.max.x, |a, b| Polygon::intersect_x(a, b, rect.max.x));
+        let points = Polygon::clip_edge(&points, |p| p.y >= rect.min.y, |a, b| Polygon::intersect_y(a, b, rect.min.y));
+        let points = Polygon::clip_edge(&points, |p| p.y <= rect.max.y, |a, b| Polygon::intersect_y(a, b, rect.max.y));
+        let holes = self.holes.iter()
+            .map(|hole| hole.clip(rect))
+            .filter(|hole| hole.points_count() > 0)
+            .collect();
+        Polygon { points, holes }
+    }
+
+    fn clip_edge(points: &[Point], inside: impl Fn(&Point) -> bool, intersect: impl Fn(&Point, &Point) -> Point) -> Vec<Point> {
+        let mut result = Vec::new();
+        if let Some(mut prev) = points.last() {
+            for current in points {
+                if inside(current) {
+                    if !inside(prev) {
+                        result.push(intersect(prev, current));
+                    }
+                    result.push(current.clone());
+                } else if inside(prev) {
+                    result.push(intersect(prev, current));
+                }
+                prev = current;
+            }
+        }
+        result
+    }
+
+    fn signed_area(&self) -> f64 {
+        self.edges().map(|(a, b)| a.cross(b)).sum::<f64>() / 2.0
+    }
+
+    // Edges of the ring, closed from the last point back to the first
+    fn edges(&self) -> impl Iterator<Item = (&Point, &Point)> {
+        let next = self.points.iter().skip(1).chain(self.points.first());
+        self.points.iter().zip(next)
+    }
+
+    fn intersect_x(a: &Point, b: &Point, x: f64) -> Point {
+        let t = (x - a.x) / (b.x - a.x);
+        Point { x, y: a.y + (b.y - a.y) * t }
+    }
+
+    fn intersect_y(a: &Point, b: &Point, y: f64) -> Point {
+        let t = (y - a.y) / (b.y - a.y);
+        Point { x: a.x + (b.x - a.x) * t, y }
+    }
+}
+
+// A square cell of the polylabel search, ordered by the best distance it may contain
+#[derive(Clone)]
+struct Cell {
+    center: Point,
+    half: f64,
+    distance: f64,
+    max: f64,
+}
+
+impl Cell {
+    fn new(center: Point, half: f64, polygon: &Polygon) -> Cell {
+        let distance = polygon.distance(&center);
+        Cell { center, half, distance, max: distance + half * std::f64::consts::SQRT_2 }
+    }
+}
+
+impl PartialEq for Cell {
+    fn eq(&self, other: &Self) -> bool {
+        self.max == other.max
+    }
+}
+
+impl Eq for Cell {}
+
+impl PartialOrd for Cell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Cell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.max.total_cmp(&other.max)
+    }
 }
 
 #[cfg(test)]
@@ -54,4 +264,123 @@ mod tests {
         assert_eq!(polygon.points[2], Point { x: 1.0, y: 1.0 });
         assert_eq!(polygon.points[3], Point { x: 0.0, y: 1.0 });
     }
+
+    #[test]
+    fn test_clip() {
+        let rect = Rectangle::new(&Point { x: 0.0, y: 0.0 }, &Point { x: 10.0, y: 10.0 });
+        let mut polygon = Polygon::new();
+        polygon.append(&Point { x: -5.0, y: -5.0 });
+        polygon.append(&Point { x: 5.0, y: -5.0 });
+        polygon.append(&Point { x: 5.0, y: 5.0 });
+        polygon.append(&Point { x: -5.0, y: 5.0 });
+
+        let clipped = polygon.clip(&rect);
+        assert_eq!(clipped.points(), &vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 5.0, y: 0.0 },
+            Point { x: 5.0, y: 5.0 },
+            Point { x: 0.0, y: 5.0 },
+        ]);
+
+        let mut polygon = Polygon::new();
+        polygon.append(&Point { x: 20.0, y: 20.0 });
+        polygon.append(&Point { x: 30.0, y: 20.0 });
+        polygon.append(&Point { x: 30.0, y: 30.0 });
+        assert_eq!(polygon.clip(&rect).points_count(), 0);
+    }
+
+    #[test]
+    fn test_area() {
+        let mut polygon = Polygon::new();
+        polygon.append(&Point { x: 0.0, y: 0.0 });
+        polygon.append(&Point { x: 4.0, y: 0.0 });
+        polygon.append(&Point { x: 4.0, y: 2.0 });
+        polygon.append(&Point { x: 0.0, y: 2.0 });
+        polygon.append(&Point { x: 0.0, y: 0.0 });
+        assert_eq!(polygon.area(), 8.0);
+        assert_eq!(polygon.centroid(), Point { x: 2.0, y: 1.0 });
+    }
+
+    #[test]
+    fn test_geodesic_area() {
+        // A tenth of a degree around Wuhan is about 9.6 by 11.1 km
+        let mut polygon = Polygon::new();
+        polygon.append(&Point { x: 114.3, y: 30.5 });
+        polygon.append(&Point { x: 114.4, y: 30.5 });
+        polygon.append(&Point { x: 114.4, y: 30.6 });
+        polygon.append(&Point { x: 114.3, y: 30.6 });
+        polygon.append(&Point { x: 114.3, y: 30.5 });
+        let area = polygon.geodesic_area();
+        assert!((106_000_000.0..107_000_000.0).contains(&area));
+
+        // A hole of a quarter of it, cut in the south-west corner
+        let mut hole = Polygon::new();
+        hole.append(&Point { x: 114.3, y: 30.5 });
+        hole.append(&Point { x: 114.35, y: 30.5 });
+        hole.append(&Point { x: 114.35, y: 30.55 });
+        hole.append(&Point { x: 114.3, y: 30.55 });
+        let hole_area = hole.geodesic_area();
+        polygon.add_hole(hole);
+        assert!((polygon.geodesic_area() - (area - hole_area)).abs() < 1.0);
+        assert!((79_000_000.0..81_000_000.0).contains(&polygon.geodesic_area()));
+    }
+
+    #[test]
+    fn test_contains() {
+        let mut polygon = Polygon::new();
+        polygon.append(&Point { x: 0.0, y: 0.0 });
+        polygon.append(&Point { x: 4.0, y: 0.0 });
+        polygon.append(&Point { x: 4.0, y: 2.0 });
+        polygon.append(&Point { x: 0.0, y: 2.0 });
+        assert!(polygon.contains(&Point { x: 1.0, y: 1.0 }));
+        assert!(!polygon.contains(&Point { x: 5.0, y: 1.0 }));
+        assert_eq!(polygon.distance(&Point { x: 1.0, y: 1.0 }), 1.0);
+        assert_eq!(polygon.distance(&Point { x: 6.0, y: 1.0 }), -2.0);
+    }
+
+    #[test]
+    fn test_pole_of_inaccessibility() {
+        // L shape, its center and centroid are outside or close to the border
+        let mut polygon = Polygon::new();
+        polygon.append(&Point { x: 0.0, y: 0.0 });
+        polygon.append(&Point { x: 10.0, y: 0.0 });
+        polygon.append(&Point { x: 10.0, y: 2.0 });
+        polygon.append(&Point { x: 2.0, y: 2.0 });
+        polygon.append(&Point { x: 2.0, y: 10.0 });
+        polygon.append(&Point { x: 0.0, y: 10.0 });
+        assert!(!polygon.contains(&polygon.bbox().center()));
+
+        let pole = polygon.pole_of_inaccessibility(0.01);
+        assert!(polygon.contains(&pole));
+        assert!(polygon.distance(&pole) > 0.99);
+    }
+
+    #[test]
+    fn test_holes() {
+        // A ring, its centroid is in the hole
+        let mut polygon = Polygon::new();
+        polygon.append(&Point { x: 0.0, y: 0.0 });
+        polygon.append(&Point { x: 10.0, y: 0.0 });
+        polygon.append(&Point { x: 10.0, y: 10.0 });
+        polygon.append(&Point { x: 0.0, y: 10.0 });
+        let mut hole = Polygon::new();
+        hole.append(&Point { x: 2.0, y: 2.0 });
+        hole.append(&Point { x: 2.0, y: 8.0 });
+        hole.append(&Point { x: 8.0, y: 8.0 });
+        hole.append(&Point { x: 8.0, y: 2.0 });
+        polygon.add_hole(hole);
+
+        let centroid = polygon.centroid();
+        assert_eq!(centroid, Point { x: 5.0, y: 5.0 });
+        assert!(!polygon.contains(&centroid));
+        assert!(polygon.contains(&Point { x: 1.0, y: 5.0 }));
+        assert_eq!(polygon.distance(&Point { x: 1.5, y: 5.0 }), 0.5);
+        assert_eq!(polygon.distance(&centroid), -3.0);
+        assert_eq!(polygon.area(), 64.0);
+
+        let pole = polygon.pole_of_inaccessibility(0.01);
+        assert!(polygon.contains(&pole));
+        assert!(polygon.distance(&pole) > 0.99);
+        assert_eq!(polygon.clip(&Rectangle::new(&Point { x: 0.0, y: 0.0 }, &Point { x: 5.0, y: 5.0 })).holes().len(), 1);
+    }
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-use super::Point;
+use super::{Line, Point, Rectangle, simplify};
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
@@ -29,9 +29,84 @@ impl Polyline {
         &self.points
     }
 
+    pub fn simplify(&self, tolerance: f64) -> Polyline {
+        Polyline { points: simplify(&self.points, tolerance) }
+    }
+
     pub fn center(&self) -> &Point {
         &self.points[(self.points.len()+1)/2]
     }
+
+    pub fn length(&self) -> f64 {
+        self.points.windows(2).map(|pair| pair[0].distance(&pair[1])).sum()
+    }
+
+    // Length in metres of a line of longitude/latitude points on the WGS-84 ellipsoid
+    pub fn geodesic_length(&self) -> f64 {
+        self.points.windows(2).map(|pair| pair[0].geodesic_distance(&pair[1])).sum()
+    }
+
+    pub fn point_at(&self, distance: f64) -> Point {
+        let mut distance = distance;
+        for pair in self.points.windows(2) {
+            let length = pair[0].distance(&pair[1]);
+            if distance <= length && length > 0.0 {
+                return Line::new(&pair[0], &pair[1]).interpolate(distance / length);
+            }
+            distance -= length;
+        }
+        self.points.last().cloned().unwrap_or(Point::uninitialized())
+    }
+
+    pub fn slice(&self, from: f64, to: f64) -> Polyline {
+        let mut slice = Polyline::new();
+        slice.append(&self.point_at(from));
+        let mut distance = 0.0;
+        for pair in self.points.windows(2) {
+            distance += pair[0].distance(&pair[1]);
+            if distance > from && distance < to {
+                slice.append(&pair[1]);
+            }
+        }
+        slice.append(&self.point_at(to));
+        slice
+    }
+
+    pub fn reversed(&self) -> Polyline {
+        Polyline { points: self.points.iter().rev().cloned().collect() }
+    }
+
+    pub fn clip(&self, rect: &Rectangle) -> Vec<Polyline> {
+        let mut pieces = Vec::new();
+        let mut piece = Polyline::new();
+        for i in 1..self.points.len() {
+            let segment = Line::new(&self.points[i-1], &self.points[i]);
+            match segment.clip(rect) {
+                Some(line) => {
+                    let (p1, p2) = line.points();
+                    if piece.points.is_empty() {
+                        piece.append(p1);
+                    }
+                    piece.append(p2);
+                    if *p2 != self.points[i] {
+                        pieces.push(piece);
+                        piece = Polyline::new();
+                    }
+                },
+                None => {
+                    if !piece.points.is_empty() {
+                        pieces.push(piece);
+                        piece = Polyline::new();
+                    }
+                },
+            }
+        }
+
+        if !piece.points.is_empty() {
+            pieces.push(piece);
+        }
+        pieces
+    }
 }
 
 #[cfg(test)]
@@ -61,5 +136,76 @@ mod tests {
         assert_eq!(polyline.center(), polyline.point(2));
         polyline.append(&Point { x: 0.0, y: 1.0 });
         assert_eq!(polyline.center(), polyline.point(3));
+        assert_eq!(polyline.length(), 3.0);
+        assert_eq!(polyline.point_at(-1.0), Point { x: 0.0, y: 0.0 });
+        assert_eq!(polyline.point_at(1.5), Point { x: 1.0, y: 0.5 });
+        assert_eq!(polyline.point_at(2.5), Point { x: 0.5, y: 1.0 });
+        assert_eq!(polyline.point_at(10.0), Point { x: 0.0, y: 1.0 });
+    }
+
+    #[test]
+    fn test_geodesic_length() {
+        let mut polyline = Polyline::new();
+        assert_eq!(polyline.geodesic_length(), 0.0);
+        polyline.append(&Point { x: 0.0, y: 0.0 });
+        polyline.append(&Point { x: 0.0, y: 1.0 });
+        polyline.append(&Point { x: 0.0, y: 2.0 });
+        let direct = Point { x: 0.0, y: 0.0 }.geodesic_distance(&Point { x: 0.0, y: 2.0 });
+        assert!((polyline.geodesic_length() - direct).abs() < 0.01);
+        assert!((polyline.geodesic_length() - 221149.45).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_slice() {
+        let mut polyline = Polyline::new();
+        polyline.append(&Point { x: 0.0, y: 0.0 });
+        polyline.append(&Point { x: 1.0, y: 0.0 });
+        polyline.append(&Point { x: 1.0, y: 1.0 });
+        polyline.append(&Point { x: 0.0, y: 1.0 });
+
+        let slice = polyline.slice(0.5, 2.5);
+        assert_eq!(slice.points(), &vec![
+            Point { x: 0.5, y: 0.0 },
+            Point { x: 1.0, y: 0.0 },
+            Point { x: 1.0, y: 1.0 },
+            Point { x: 0.5, y: 1.0 },
+        ]);
+
+        let slice = polyline.slice(1.2, 1.6);
+        assert_eq!(slice.points_count(), 2);
+
+        let reversed = slice.reversed();
+        assert_eq!(reversed.point(0), slice.point(1));
+        assert_eq!(reversed.point(1), slice.point(0));
+    }
+
+    #[test]
+    fn test_clip() {
+        let rect = Rectangle::new(&Point { x: 0.0, y: 0.0 }, &Point { x: 10.0, y: 10.0 });
+        let mut polyline = Polyline::new();
+        polyline.append(&Point { x: -5.0, y: 5.0 });
+        polyline.append(&Point { x: 5.0, y: 5.0 });
+        polyline.append(&Point { x: 5.0, y: 15.0 });
+        polyline.append(&Point { x: 8.0, y: 15.0 });
+        polyline.append(&Point { x: 8.0, y: 5.0 });
+        polyline.append(&Point { x: 9.0, y: 5.0 });
+
+        let pieces = polyline.clip(&rect);
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0].points(), &vec![
+            Point { x: 0.0, y: 5.0 },
+            Point { x: 5.0, y: 5.0 },
+            Point { x: 5.0, y: 10.0 },
+        ]);
+        assert_eq!(pieces[1].points(), &vec![
+            Point { x: 8.0, y: 10.0 },
+            Point { x: 8.0, y: 5.0 },
+            Point { x: 9.0, y: 5.0 },
+        ]);
+
+        let mut polyline = Polyline::new();
+        polyline.append(&Point { x: 20.0, y: 20.0 });
+        polyline.append(&Point { x: 30.0, y: 20.0 });
+        assert_eq!(polyline.clip(&rect).len(), 0);
     }
 }
\ No newline at end of file
@@ -27,6 +27,10 @@ impl Rectangle {
         self.max.x >= rect.min.x && self.max.y >= rect.min.y && self.min.x <= rect.max.x && self.min.y <= rect.max.y
     }
 
+    pub fn contains(&self, p: &Point) -> bool {
+        p.x >= self.min.x && p.y >= self.min.y && p.x <= self.max.x && p.y <= self.max.y
+    }
+
     pub fn area(&self) -> f64 {
         let vec = &self.max - &self.min;
         vec.x * vec.y
@@ -46,6 +50,13 @@ impl Rectangle {
             y: (self.min.y + self.max.y) / 2.0,
         }
     }
+
+    pub fn buffer(&self, distance: f64) -> Rectangle {
+        Rectangle {
+            min: Point { x: self.min.x - distance, y: self.min.y - distance },
+            max: Point { x: self.max.x + distance, y: self.max.y + distance },
+        }
+    }
 }
 
 #[cfg(test)]
@@ -79,6 +90,14 @@ mod tests {
         assert_eq!(rect1.is_intersect(&rect2), false);
     }
 
+    #[test]
+    fn test_contains() {
+        let rect = Rectangle::new(&Point { x: 0.0, y: 0.0 }, &Point { x: 1.0, y: 1.0 });
+        assert!(rect.contains(&Point { x: 0.5, y: 1.0 }));
+        assert!(!rect.contains(&Point { x: 1.5, y: 0.5 }));
+        assert!(!Rectangle::uninitialized().contains(&Point { x: 0.5, y: 0.5 }));
+    }
+
     #[test]
     fn test_area() {
         let rect = Rectangle::uninitialized();
@@ -101,4 +120,12 @@ mod tests {
         let rect = Rectangle::new(&Point { x: 3.0, y: 7.0 }, &Point { x: 9.0, y: 14.0 });
         assert_eq!(rect.center(), Point{x: 6.0, y: 10.5});
     }
+
+    #[test]
+    fn test_buffer() {
+        let rect = Rectangle::new(&Point { x: 3.0, y: 7.0 }, &Point { x: 9.0, y: 14.0 });
+        let rect = rect.buffer(2.0);
+        assert_eq!(rect.min, Point{x: 1.0, y: 5.0});
+        assert_eq!(rect.max, Point{x: 11.0, y: 16.0});
+    }
 }
\ No newline at end of file
@@ -0,0 +1,66 @@
+use super::{BaseType, Line, Point};
+
+// Douglas–Peucker, the first and last points are always kept
+pub fn simplify(points: &[Point], tolerance: BaseType) -> Vec<Point> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let line = Line::new(&points[first], &points[last]);
+        let mut max_distance = 0.0;
+        let mut index = first;
+        for (i, point) in points.iter().enumerate().take(last).skip(first + 1) {
+            let distance = line.distance(point);
+            if distance > max_distance {
+                max_distance = distance;
+                index = i;
+            }
+        }
+
+        if max_distance > tolerance {
+            keep[index] = true;
+            stack.push((first, index));
+            stack.push((index, last));
+        }
+    }
+
+    points.iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(point, _)| point.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simplify() {
+        let points = vec![
+            Point::new(0.0, 0.0),
+            Point::new(1.0, 0.1),
+            Point::new(2.0, -0.1),
+            Point::new(3.0, 5.0),
+            Point::new(4.0, 6.0),
+            Point::new(5.0, 7.0),
+            Point::new(6.0, 8.1),
+            Point::new(7.0, 9.0),
+        ];
+
+        assert_eq!(simplify(&points, 1.0), vec![
+            Point::new(0.0, 0.0),
+            Point::new(2.0, -0.1),
+            Point::new(3.0, 5.0),
+            Point::new(7.0, 9.0),
+        ]);
+        assert_eq!(simplify(&points, 0.01).len(), 7);
+        assert_eq!(simplify(&points[..2], 100.0), points[..2].to_vec());
+    }
+}
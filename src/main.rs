@@ -3,310 +3,431 @@ mod geography;
 mod tile;
 mod xml;
 mod cache;
+mod style;
 
-use std::sync::Mutex;
+use std::{collections::HashMap, path::PathBuf, sync::{Arc, Mutex}, thread, time::{Duration, SystemTime}};
 
 use actix_web::{get, web, App, HttpResponse, HttpServer, Responder};
 use cache::{Cache, MemoryCache};
-use geography::ShapeFile;
+use geography::{Crs, Node, ShapeFile};
 use geometry::{Shape, Point, Rectangle};
-use tile::{Proj, SvgRender,PolygonProps, PolylineProps, SvgTile, TextProps, Tile};
-
-
-#[allow(dead_code)]
-pub mod colors {
-    pub const REGION_FILL_COLOR: &str = "#d3f8e2";
-    pub const REGION_BORDER_COLOR: &str = "#5491f5";
-    pub const REGION_TEXT_COLOR: &str = "#5491f5";
-
-    pub const WATER_FILL_COLOR: &str = "#90daee";
-    pub const WATER_BORDER_COLOR: &str = "#90daee";
-    pub const WATER_TEXT_COLOR: &str = "#000000";
-
-    pub const LAND_FILL_COLOR: &str = "#f5f0e5";
-    pub const LAND_BORDER_COLOR: &str = "#aab9c9";
-    pub const LAND_TEXT_COLOR: &str = "#000000";
-
-    pub const ROAD_FILL_COLOR: &str = "#aab9c9";
-    pub const ROAD_BORDER_COLOR: &str = "#78909c";
-    pub const ROAD_TEXT_COLOR: &str = "#000000";
-
-    pub const BUILDING_FILL_COLOR: &str = "#e8e9ed";
-    pub const BUILDING_BORDER_COLOR: &str = "#aab9c9";
-    pub const BUILDING_TEXT_COLOR: &str = "#000000";
-}
-
-#[allow(dead_code)]
-pub mod priorities {
-    pub const REGION_FILL_PRIORITY: i32 = 10;
-    pub const REGION_BORDER_PRIORITY: i32 = 20;
-    pub const REGION_TEXT_PRIORITY: i32 = 30;
-
-    pub const WATER_FILL_PRIORITY: i32 = 10;
-    pub const WATER_BORDER_PRIORITY: i32 = 20;
-    pub const WATER_TEXT_PRIORITY: i32 = 30;
-
-    pub const LAND_FILL_PRIORITY: i32 = 10;
-    pub const LAND_BORDER_PRIORITY: i32 = 20;
-    pub const LAND_TEXT_PRIORITY: i32 = 30;
-
-    pub const ROAD_FILL_PRIORITY: i32 = 10;
-    pub const ROAD_BORDER_PRIORITY: i32 = 20;
-    pub const ROAD_TEXT_PRIORITY: i32 = 30;
-
-    pub const BUILDING_FILL_PRIORITY: i32 = 10;
-    pub const BUILDING_BORDER_PRIORITY: i32 = 20;
-    pub const BUILDING_TEXT_PRIORITY: i32 = 30;
-}
-
-#[allow(dead_code)]
-pub mod zooms {
-    pub const SHOW_POLYLINE: u64 = 10;
-    pub const SHOW_TEXT: u64 = 10;
-}
+use serde::Deserialize;
+use style::{Layer, Rule, Style};
+use tile::{consts::MAX_ZOOM, Datum, Proj, SvgRender, SvgTile, Tile};
+
+// Every JSON file in the directory is a style, named after the file
+const STYLE_DIR: &str = "styles";
+// Style of the routes without a style name
+const DEFAULT_STYLE: &str = "light";
+// How often the style files are checked for changes
+const STYLE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+// Shapefiles by source name, with an optional coordinate reference system overriding their .prj
+const SOURCES: [(&str, &str, Option<&str>); 5] = [
+    ("region", "resource/wuhan/wuhan_region.shp", None),
+    ("water", "resource/wuhan/wuhan_water.shp", None),
+    ("land", "resource/wuhan/wuhan_land.shp", None),
+    ("road", "resource/wuhan/wuhan_road.shp", None),
+    ("building", "resource/wuhan/wuhan_building.shp", None),
+];
 
 struct AppState {
-    region: Mutex<ShapeFile>,
-    water: Mutex<ShapeFile>,
-    land: Mutex<ShapeFile>,
-    road: Mutex<ShapeFile>,
-    building: Mutex<ShapeFile>,
+    // Shapefiles by name, drawn by the style layers with the same source
+    sources: HashMap<String, ShapeFile>,
+    // Shared with the renders, so that a style is not locked while a tile is drawn
+    styles: Mutex<HashMap<String, Arc<Style>>>,
 
-    render: Mutex<SvgRender>,
+    render: SvgRender,
     cache: Mutex<MemoryCache>,
+    metatile: u64,
 }
 
 impl AppState {
     pub fn new() -> AppState {
         AppState { 
-            region: Mutex::new(ShapeFile::new()), 
-            water: Mutex::new(ShapeFile::new()), 
-            land: Mutex::new(ShapeFile::new()),
-            road: Mutex::new(ShapeFile::new()), 
-            building: Mutex::new(ShapeFile::new()),
+            sources: HashMap::new(),
+            styles: Mutex::new(HashMap::new()),
 
-            render: Mutex::new(SvgRender::new()),
+            render: SvgRender::new(),
             cache: Mutex::new(MemoryCache::new()),
+            metatile: 1,
         }
     }
 
-    pub fn load_region(&mut self, file: &str) {
-        self.region.get_mut().unwrap().load(file);
-    }
-
-    pub fn load_water(&mut self, file: &str) {
-        self.water.get_mut().unwrap().load(file);
-    }
-
-    pub fn load_land(&mut self, file: &str) {
-        self.land.get_mut().unwrap().load(file);
+    // Tiles are rendered in blocks of size x size, size must be a power of 2, 1 disables it
+    pub fn set_metatile(&mut self, size: u64) {
+        self.metatile = size;
     }
 
-    pub fn load_road(&mut self, file: &str) {
-        self.road.get_mut().unwrap().load(file);
+    // Coordinates are read in the given coordinate reference system whatever the .prj says, like EPSG:32650,
+    // or in the one of the .prj of the file without it
+    pub fn load_source(&mut self, name: &str, file: &str, crs: Option<&str>) -> Result<(), String> {
+        let mut shapes = ShapeFile::new();
+        if let Some(crs) = crs {
+            shapes.set_crs(Crs::parse(crs)?);
+        }
+        shapes.load(file)?;
+        self.sources.insert(String::from(name), shapes);
+        Ok(())
     }
 
-    pub fn load_building(&mut self, file: &str) {
-        self.building.get_mut().unwrap().load(file);
+    // A style that fails to load is reported and skipped, it is only an error when no style loads
+    pub fn load_styles(&mut self, dir: &str) -> Result<(), String> {
+        let styles = self.styles.get_mut().unwrap();
+        for (name, file) in style_files(dir) {
+            match Style::load(&file.to_string_lossy()) {
+                Ok(style) => {
+                    styles.insert(name, Arc::new(style));
+                },
+                Err(err) => eprintln!("failed to load style: {}", err),
+            }
+        }
+        if styles.is_empty() {
+            return Err(format!("no style loaded from '{}'", dir));
+        }
+        Ok(())
     }
 
-    pub fn draw_tile(&self, tile: &mut SvgTile, shapes: &ShapeFile, polygon_props: &PolygonProps, polyline_props: &PolylineProps, text_props: &TextProps) {
+    pub fn draw_tile(&self, tile: &mut SvgTile, shapes: &ShapeFile, layer: &Layer) {
+        let world = tile.proj().world_width(tile.z());
         for node in shapes.nodes() {
+            let min = tile.shift((node.info.rect.min.x, node.info.rect.min.y));
+            let max = tile.shift((node.info.rect.max.x, node.info.rect.max.y));
             let rect = Rectangle::new(
                 &Point{
-                    x: tile.proj().longitude_to_x(node.info.rect.min.x, tile.z() as f64),
-                    y: tile.proj().latitude_to_y(node.info.rect.max.y, tile.z() as f64),
+                    x: tile.proj().longitude_to_x(min.0, tile.z() as f64),
+                    y: tile.proj().latitude_to_y(max.1, tile.z() as f64),
                 },
                 &Point {
-                    x: tile.proj().longitude_to_x(node.info.rect.max.x, tile.z() as f64),
-                    y: tile.proj().latitude_to_y(node.info.rect.min.y, tile.z() as f64),
+                    x: tile.proj().longitude_to_x(max.0, tile.z() as f64),
+                    y: tile.proj().latitude_to_y(min.1, tile.z() as f64),
                 }
             );
-    
-            if !rect.is_intersect(&tile.rect()) || rect.area() < 10.0 {
+            if !matches!(node.shape, Shape::Point(_)) && rect.area() < 10.0 {
                 continue;
             }
 
-            match &node.shape {
-                Shape::Polyline(polyline) => {
-                    if tile.z() > zooms::SHOW_TEXT && !node.info.name.is_empty() {
-                        tile.append_text_path(polyline, &node.info.name, polyline_props, text_props);
-                    } else if tile.z() > zooms::SHOW_POLYLINE {
+            // Features crossing the antimeridian go beyond ±180 degrees, the part beyond is drawn a world back
+            let copies: Vec<f64> = [-1.0, 0.0, 1.0].into_iter()
+                .filter(|copy| {
+                    let min = Point::new(rect.min.x + copy * world, rect.min.y);
+                    let max = Point::new(rect.max.x + copy * world, rect.max.y);
+                    Rectangle::new(&min, &max).is_intersect(&tile.rect())
+                })
+                .collect();
+            if copies.is_empty() {
+                continue;
+            }
+
+            let rule = match layer.find(&node.info, tile.z()) {
+                Some(rule) => rule,
+                None => continue,
+            };
+            for copy in copies {
+                tile.set_world_offset(copy * 360.0);
+                self.draw_node(tile, node, rule, &rect);
+            }
+        }
+        tile.set_world_offset(0.0);
+    }
+
+    fn draw_node(&self, tile: &mut SvgTile, node: &Node, rule: &Rule, rect: &Rectangle) {
+        let label = rule.label(&node.info);
+        let text_props = rule.text.as_ref().filter(|_| tile.z() >= rule.label_min_zoom && !label.is_empty());
+
+        match &node.shape {
+            Shape::Point(point) => {
+                if let Some(point_props) = &rule.point {
+                    tile.append_point(point, point_props);
+                    if let Some(text_props) = text_props {
+                        tile.append_point_text(point, &label, point_props, text_props);
+                    }
+                }
+            },
+
+            Shape::Polyline(polyline) => {
+                if let Some(polyline_props) = &rule.polyline {
+                    if let Some(text_props) = text_props {
+                        tile.append_text_path(polyline, &label, polyline_props, text_props);
+                    } else {
                         tile.append_polyline(polyline, polyline_props);
                     }
-                },
+                }
+            },
 
-                Shape::Polygon(polygon) => {
+            Shape::Polygon(polygon) => {
+                if let Some(polygon_props) = &rule.polygon {
                     tile.append_polygon(polygon, polygon_props);
-                    let text_size = SvgTile::text_size(&node.info.name, text_props);
-                    if !node.info.name.is_empty() && rect.width() > text_size.width() && rect.height() > text_size.height() {
-                        tile.append_text(&node.info.rect.center(), &node.info.name, text_props);
+                }
+                if let (Some(anchor), Some(text_props)) = (&node.info.anchor, text_props) {
+                    let text_size = tile.text_size(&label, text_props);
+                    if rect.width() > text_size.width() && rect.height() > text_size.height() {
+                        tile.append_text(anchor, &label, text_props);
                     }
-                },
-    
-                _ => {},
+                }
+            },
+
+            _ => {},
+        }
+    }
+
+    pub fn draw_layers(&self, tile: &mut SvgTile, style: &Style) {
+        let z = tile.z();
+        for layer in style.layers.iter().filter(|layer| layer.is_visible(z)) {
+            if let Some(shapes) = self.sources.get(&layer.source) {
+                tile.set_source_datum(layer.datum);
+                self.draw_tile(tile, shapes, layer);
             }
         }
+        tile.set_source_datum(Datum::default());
     }
+}
+
+
+#[derive(Deserialize)]
+struct MapsQuery {
+    // Draws tile borders, z/x/y and a graticule over the map when set to 1
+    #[serde(default)]
+    debug: u8,
+    // Tile scheme, webmercator (EPSG:3857) by default or geographic (EPSG:4326)
+    proj: Option<String>,
+    // Datum the data is shifted to, wgs84 by default, gcj02 to overlay on Amap or bd09 on Baidu
+    datum: Option<String>,
+}
+
+// How a tile is drawn, besides its style
+struct TileOptions {
+    proj: Proj,
+    datum: Datum,
+    debug: bool,
+}
 
-    
+impl MapsQuery {
+    fn options(&self) -> Result<TileOptions, HttpResponse> {
+        let proj = match &self.proj {
+            Some(name) => Proj::parse(name).ok_or_else(|| HttpResponse::BadRequest().body(format!("unknown projection '{}'", name)))?,
+            None => Proj::default(),
+        };
+        let datum = match &self.datum {
+            Some(name) => Datum::parse(name).ok_or_else(|| HttpResponse::BadRequest().body(format!("unknown datum '{}'", name)))?,
+            None => Datum::default(),
+        };
+        Ok(TileOptions { proj, datum, debug: self.debug == 1 })
+    }
 }
 
+impl TileOptions {
+    // Tiles drawn with different options are cached apart, under the prefix of the style so that they are purged with it
+    fn cache_prefix(&self, style_name: &str) -> String {
+        let mut prefix = format!("tile:{}:{}:{}:", style_name, self.proj.name(), self.datum.name());
+        if self.debug {
+            prefix.push_str("debug:");
+        }
+        prefix
+    }
+}
 
 #[get("/maps/{z}/{x}/{y}")]
-async fn maps(state: web::Data<AppState>, path: web::Path<(u64, u64, u64)>) -> impl Responder {
-    let proj = Proj::default();
+async fn maps(state: web::Data<AppState>, path: web::Path<(u64, u64, u64)>, query: web::Query<MapsQuery>) -> impl Responder {
     let (z, x, y) = path.into_inner();
-    let id = format!("tile:{}-{}-{}", z, x, y);
+    match query.options() {
+        Ok(options) => render_tile(&state, DEFAULT_STYLE, &options, z, x, y),
+        Err(response) => response,
+    }
+}
+
+#[get("/maps/{style}/{z}/{x}/{y}")]
+async fn styled_maps(state: web::Data<AppState>, path: web::Path<(String, u64, u64, u64)>, query: web::Query<MapsQuery>) -> impl Responder {
+    let (style, z, x, y) = path.into_inner();
+    match query.options() {
+        Ok(options) => render_tile(&state, &style, &options, z, x, y),
+        Err(response) => response,
+    }
+}
+
+// TMS addressing, rows are counted from the south
+#[get("/tms/{style}/{z}/{x}/{y}")]
+async fn tms_maps(state: web::Data<AppState>, path: web::Path<(String, u64, u64, u64)>, query: web::Query<MapsQuery>) -> impl Responder {
+    let (style, z, x, y) = path.into_inner();
+    let options = match query.options() {
+        Ok(options) => options,
+        Err(response) => return response,
+    };
+    match options.proj.tms_to_xyz(z, y) {
+        Some(y) => render_tile(&state, &style, &options, z, x, y),
+        None => HttpResponse::NotFound().body(format!("no tile {}/{}/{}", z, x, y)),
+    }
+}
+
+// Bing quadkey addressing, only for the Web Mercator projection
+#[get("/quadkey/{style}/{quadkey}")]
+async fn quadkey_maps(state: web::Data<AppState>, path: web::Path<(String, String)>, query: web::Query<MapsQuery>) -> impl Responder {
+    let (style, quadkey) = path.into_inner();
+    let options = match query.options() {
+        Ok(options) => options,
+        Err(response) => return response,
+    };
+    match options.proj.quadkey_to_tile(&quadkey) {
+        Ok((z, x, y)) => render_tile(&state, &style, &options, z, x, y),
+        Err(err) => HttpResponse::BadRequest().body(err),
+    }
+}
+
+fn render_tile(state: &AppState, style_name: &str, options: &TileOptions, z: u64, x: u64, y: u64) -> HttpResponse {
+    if z > MAX_ZOOM {
+        return HttpResponse::NotFound().body(format!("no tile {}/{}/{}, the maximum zoom is {}", z, x, y, MAX_ZOOM));
+    }
+    // Columns wrap around the antimeridian, so that the map repeats on both sides
+    let x = options.proj.wrap_x(z, x);
+    let (_, rows) = options.proj.tile_count(z);
+    if y >= rows {
+        return HttpResponse::NotFound().body(format!("no tile {}/{}/{}", z, x, y));
+    }
+
+    let prefix = options.cache_prefix(style_name);
+    let tile_id = |x: u64, y: u64| format!("{}{}-{}-{}", prefix, z, x, y);
+    // The locks are only held to look up and save, tiles are rendered without them
+    let mut size = state.metatile.min(1 << z);
+    {
+        let cache = state.cache.lock().unwrap();
+        if let Some(data) = cache.get(&tile_id(x, y)) {
+            return HttpResponse::Ok()
+                .append_header(("Access-Control-Allow-Origin", "*"))
+                .content_type("image/png")
+                .body(data.clone());
+        }
+        // Only the tile is rendered when the rest of its metatile is still cached
+        let (left, top) = (x - x % size, y - y % size);
+        let siblings_cached = (0..size * size)
+            .map(|i| (left + i % size, top + i / size))
+            .all(|(tile_x, tile_y)| (tile_x, tile_y) == (x, y) || cache.has(&tile_id(tile_x, tile_y)));
+        if siblings_cached {
+            size = 1;
+        }
+    }
+    let style = match state.styles.lock().unwrap().get(style_name) {
+        Some(style) => style.clone(),
+        None => return HttpResponse::NotFound().body(format!("unknown style '{}'", style_name)),
+    };
+
+    let (left, top) = (x - x % size, y - y % size);
+    let mut tile = SvgTile::metatile(left, top, z, size, options.proj, state.render.metrics());
+    tile.set_datum(options.datum);
+    if let Some(background) = &style.background {
+        tile.append_background(background);
+    }
+    state.draw_layers(&mut tile, &style);
+    tile.place_labels();
+    if options.debug {
+        tile.append_debug();
+    }
+    tile.sort_tags();
+
+    let pngs = match state.render.to_pngs(&tile.dump(), size) {
+        Some(pngs) => pngs,
+        None => return HttpResponse::InternalServerError().body(format!("failed to render tile {}/{}/{}", z, x, y)),
+    };
+    let mut data = Vec::new();
     let mut cache = state.cache.lock().unwrap();
-    if let Some(data) = cache.get(&id) {
-        HttpResponse::Ok()
-            .append_header(("Access-Control-Allow-Origin", "*"))
-            .content_type("image/png")
-            .body(data.clone())
-    } else {
-        let mut tile = SvgTile::new(x, y, z, proj);
-        state.draw_tile(
-            &mut tile, 
-            &state.region.lock().unwrap(), 
-            &PolygonProps::new(
-                colors::REGION_FILL_COLOR, 
-                colors::REGION_BORDER_COLOR, 
-                3, 
-                priorities::REGION_FILL_PRIORITY, 
-                priorities::REGION_BORDER_PRIORITY
-            ), 
-            &PolylineProps::new(
-                colors::REGION_BORDER_COLOR, 
-                3, 
-                priorities::REGION_BORDER_PRIORITY
-            ), 
-            &TextProps::new(
-                colors::REGION_TEXT_COLOR, 
-                32, 
-                700, 
-                priorities::REGION_TEXT_PRIORITY
-            )
-        );
-
-        state.draw_tile(
-            &mut tile, 
-            &state.water.lock().unwrap(), 
-            &PolygonProps::new(
-                colors::WATER_FILL_COLOR, 
-                colors::WATER_BORDER_COLOR, 
-                1, 
-                priorities::WATER_FILL_PRIORITY,
-                priorities::WATER_BORDER_PRIORITY
-            ), 
-            &PolylineProps::new(
-                colors::WATER_BORDER_COLOR, 
-                1, 
-                priorities::WATER_BORDER_PRIORITY
-            ), 
-            &TextProps::new(
-                colors::WATER_TEXT_COLOR, 
-                20, 
-                700, 
-                priorities::WATER_TEXT_PRIORITY
-            )
-        );
-        
-        state.draw_tile(
-            &mut tile, 
-            &state.land.lock().unwrap(), 
-            &PolygonProps::new(
-                colors::LAND_FILL_COLOR, 
-                colors::LAND_BORDER_COLOR, 
-                1, 
-                priorities::LAND_FILL_PRIORITY,
-                priorities::LAND_BORDER_PRIORITY
-            ), 
-            &PolylineProps::new(
-                colors::LAND_BORDER_COLOR, 
-                3,
-                priorities::LAND_BORDER_PRIORITY
-            ), 
-            &TextProps::new(
-                colors::LAND_TEXT_COLOR, 
-                20, 
-                700, 
-                priorities::LAND_TEXT_PRIORITY
-            )
-        );
-
-        state.draw_tile(
-            &mut tile, 
-            &state.road.lock().unwrap(), 
-            &PolygonProps::new(
-                colors::ROAD_FILL_COLOR, 
-                colors::ROAD_BORDER_COLOR, 
-                1, 
-                priorities::REGION_FILL_PRIORITY, 
-                priorities::REGION_BORDER_PRIORITY
-            ), 
-            &PolylineProps::new(
-                colors::ROAD_BORDER_COLOR, 
-                3, 
-                priorities::ROAD_BORDER_PRIORITY
-            ), 
-            &TextProps::new(
-                colors::ROAD_TEXT_COLOR, 
-                20, 
-                900, 
-                priorities::ROAD_TEXT_PRIORITY
-            )
-        );
-
-        state.draw_tile(
-            &mut tile, 
-            &state.building.lock().unwrap(), 
-            &PolygonProps::new(
-                colors::BUILDING_FILL_COLOR, 
-                colors::BUILDING_BORDER_COLOR, 
-                1, 
-                priorities::BUILDING_FILL_PRIORITY,
-                priorities::BUILDING_BORDER_PRIORITY
-            ), 
-            &PolylineProps::new(
-                colors::BUILDING_BORDER_COLOR, 
-                3, 
-                priorities::BUILDING_BORDER_PRIORITY
-            ), 
-            &TextProps::new(
-                colors::BUILDING_TEXT_COLOR, 
-                20, 
-                700, 
-                priorities::BUILDING_TEXT_PRIORITY
-            )
-        );
-
-        tile.sort_tags();
-        let render = state.render.lock().unwrap();
-        let data = render.to_png(&tile.dump()).unwrap();
-        cache.save(&id, data.clone());
-        HttpResponse::Ok()
-            .append_header(("Access-Control-Allow-Origin", "*"))
-            .content_type("image/png")
-            .body(data)
+    for (i, png) in pngs.into_iter().enumerate() {
+        let (tile_x, tile_y) = (left + i as u64 % size, top + i as u64 / size);
+        if (tile_x, tile_y) == (x, y) {
+            data = png;
+        } else {
+            cache.save(&tile_id(tile_x, tile_y), png);
+        }
+    }
+    cache.save(&tile_id(x, y), data.clone());
+    HttpResponse::Ok()
+        .append_header(("Access-Control-Allow-Origin", "*"))
+        .content_type("image/png")
+        .body(data)
+}
+
+// The JSON files in a directory, by file name without the extension
+fn style_files(dir: &str) -> Vec<(String, PathBuf)> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+            files.push((String::from(name), path.clone()));
+        }
     }
+    files
+}
+
+// Reloads a style when its file changes or appears and purges the tiles drawn with the old one.
+// A style that fails to load is reported and the old one is kept.
+fn watch_styles(state: web::Data<AppState>, dir: &str) {
+    let dir = String::from(dir);
+    let modified = |file: &PathBuf| std::fs::metadata(file).and_then(|meta| meta.modified()).ok();
+    let mut last_modified: HashMap<PathBuf, Option<SystemTime>> = style_files(&dir)
+        .into_iter()
+        .map(|(_, file)| {
+            let time = modified(&file);
+            (file, time)
+        })
+        .collect();
+    thread::spawn(move || loop {
+        thread::sleep(STYLE_POLL_INTERVAL);
+        for (name, file) in style_files(&dir) {
+            let current = modified(&file);
+            if last_modified.get(&file) == Some(&current) {
+                continue;
+            }
+            last_modified.insert(file.clone(), current);
+
+            match Style::load(&file.to_string_lossy()) {
+                Ok(style) => {
+                    state.styles.lock().unwrap().insert(name.clone(), Arc::new(style));
+                    state.cache.lock().unwrap().delete_prefix(&format!("tile:{}:", name));
+                    println!("reloaded style {}", name);
+                },
+                Err(err) => eprintln!("failed to reload style: {}", err),
+            }
+        }
+
+        // The styles whose file is gone are dropped with their tiles
+        let deleted: Vec<PathBuf> = last_modified.keys().filter(|file| !file.exists()).cloned().collect();
+        for file in deleted {
+            last_modified.remove(&file);
+            if let Some(name) = file.file_stem().and_then(|name| name.to_str()) {
+                state.styles.lock().unwrap().remove(name);
+                state.cache.lock().unwrap().delete_prefix(&format!("tile:{}:", name));
+                println!("removed style {}", name);
+            }
+        }
+    });
 }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     let mut app_state: AppState = AppState::new();
-        app_state.load_region("resource/wuhan/wuhan_region.shp");
-        app_state.load_water("resource/wuhan/wuhan_water.shp");
-        app_state.load_land("resource/wuhan/wuhan_land.shp");
-        app_state.load_road("resource/wuhan/wuhan_road.shp");
-        app_state.load_building("resource/wuhan/wuhan_building.shp");
+        for (name, file, crs) in SOURCES {
+            if let Err(err) = app_state.load_source(name, file, crs) {
+                eprintln!("failed to load source {}: {}", name, err);
+            }
+        }
+        app_state.load_styles(STYLE_DIR).map_err(std::io::Error::other)?;
+        app_state.set_metatile(4);
 
     let app_data = web::Data::new(app_state);
+    watch_styles(app_data.clone(), STYLE_DIR);
 
     HttpServer::new(move || {
         App::new()
             .app_data(app_data.clone())
             .service(maps)
+            .service(styled_maps)
+            .service(tms_maps)
+            .service(quadkey_maps)
     })
     .bind(("127.0.0.1", 1995))?
     .run()
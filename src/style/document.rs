@@ -0,0 +1,512 @@
+use serde::{Deserialize, Deserializer};
+use crate::tile::{BackgroundProps, Datum, Marker, Pattern, PatternKind, PointProps, PolygonProps, PolylineProps, Stops, TextProps};
+use super::{Expr, Layer, Rule};
+
+// A map style, loaded from a JSON document like:
+// {
+//     "name": "light",
+//     "background": { "color": "#f5f0e5" },
+//     "layers": [
+//         {
+//             "id": "road",
+//             "source": "road",
+//             "rules": [
+//                 { "filter": "fclass == 'motorway'", "minzoom": 11, "polyline": { "color": "#e8a04d", "width": 5 } }
+//             ]
+//         }
+//     ]
+// }
+#[allow(dead_code)]
+pub struct Style {
+    pub name: String,
+    pub background: Option<BackgroundProps>,
+    pub layers: Vec<Layer>,
+}
+
+#[allow(dead_code)]
+impl Style {
+    pub fn new() -> Style {
+        Style { name: String::new(), background: None, layers: Vec::new() }
+    }
+
+    pub fn load(file: &str) -> Result<Style, String> {
+        let text = std::fs::read_to_string(file).map_err(|err| format!("{}: {}", file, err))?;
+        Style::parse(&text).map_err(|err| format!("{}: {}", file, err))
+    }
+
+    pub fn parse(text: &str) -> Result<Style, String> {
+        let document: StyleDocument = serde_json::from_str(text).map_err(|err| err.to_string())?;
+        let mut layers = Vec::new();
+        let mut background = None;
+        if let Some(background_document) = document.background {
+            let mut props = BackgroundProps::new(&background_document.color);
+            if let Some(pattern) = background_document.pattern {
+                props.pattern = Some(pattern.into_pattern()?);
+            }
+            background = Some(props);
+
+            if let Some(base) = background_document.base {
+                let mut layer = Layer::new(BASE_LAYER);
+                layer.source = base.source;
+                layer.add(Rule {
+                    polygon: Some(PolygonProps::new(&base.color, &base.color, 0, BASE_PRIORITY, BASE_PRIORITY)),
+                    ..Rule::new()
+                });
+                layers.push(layer);
+            }
+        }
+
+        for layer_document in document.layers {
+            let mut layer = Layer::new(&layer_document.id);
+            if let Some(source) = layer_document.source {
+                layer.source = source;
+            }
+            if let Some(min_zoom) = layer_document.minzoom {
+                layer.min_zoom = min_zoom;
+            }
+            if let Some(max_zoom) = layer_document.maxzoom {
+                layer.max_zoom = max_zoom;
+            }
+            if let Some(datum) = layer_document.datum {
+                layer.datum = Datum::parse(&datum).ok_or_else(|| format!("layer '{}': unknown datum '{}'", layer.name, datum))?;
+            }
+            for rule in layer_document.rules {
+                layer.add(rule.into_rule().map_err(|err| format!("layer '{}': {}", layer.name, err))?);
+            }
+            layers.push(layer);
+        }
+        Ok(Style { name: document.name, background, layers })
+    }
+}
+
+// Name of the layer made for the base fill of the background
+const BASE_LAYER: &str = "base";
+// The base fill is above the background and below every layer
+const BASE_PRIORITY: i32 = i32::MIN + 1;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StyleDocument {
+    #[serde(default)]
+    name: String,
+    background: Option<BackgroundDocument>,
+    layers: Vec<LayerDocument>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BackgroundDocument {
+    color: String,
+    pattern: Option<PatternDocument>,
+    base: Option<BaseDocument>,
+}
+
+// The polygons of a source filled over the background, like land polygons over an ocean
+// background, or water polygons over a land background
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BaseDocument {
+    source: String,
+    color: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LayerDocument {
+    id: String,
+    // Defaults to the id
+    source: Option<String>,
+    minzoom: Option<u64>,
+    maxzoom: Option<u64>,
+    // Datum of the source, wgs84 by default, like gcj02 for data digitized over Amap
+    datum: Option<String>,
+    rules: Vec<RuleDocument>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct RuleDocument {
+    filter: Option<String>,
+    #[serde(rename = "minzoom")]
+    min_zoom: Option<u64>,
+    #[serde(rename = "maxzoom")]
+    max_zoom: Option<u64>,
+    label: Option<String>,
+    #[serde(rename = "label-minzoom")]
+    label_min_zoom: Option<u64>,
+    point: Option<PointDocument>,
+    polyline: Option<PolylineDocument>,
+    polygon: Option<PolygonDocument>,
+    text: Option<TextDocument>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct PointDocument {
+    // "circle" or "square", ignored when an icon is given
+    #[serde(default = "default_marker")]
+    marker: String,
+    icon: Option<String>,
+    size: Stops,
+    #[serde(default = "default_zero")]
+    rotation: Stops,
+    fill_color: String,
+    border_color: String,
+    #[serde(default = "default_zero")]
+    border_width: Stops,
+    #[serde(default)]
+    priority: i32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct PolylineDocument {
+    color: String,
+    width: Stops,
+    #[serde(default)]
+    priority: i32,
+    #[serde(default)]
+    dash: Vec<f64>,
+    #[serde(default = "default_opacity")]
+    opacity: Stops,
+    cap: Option<String>,
+    join: Option<String>,
+    casing_color: Option<String>,
+    #[serde(default = "default_zero")]
+    casing_width: Stops,
+    // Defaults to just under the line
+    casing_priority: Option<i32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct PolygonDocument {
+    fill_color: String,
+    border_color: String,
+    #[serde(default = "default_zero")]
+    border_width: Stops,
+    #[serde(default)]
+    fill_priority: i32,
+    #[serde(default)]
+    border_priority: i32,
+    #[serde(default = "default_opacity")]
+    fill_opacity: Stops,
+    #[serde(default = "default_opacity")]
+    border_opacity: Stops,
+    #[serde(default)]
+    border_dash: Vec<f64>,
+    pattern: Option<PatternDocument>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PatternDocument {
+    // "hatch", "cross" or "dots"
+    #[serde(rename = "type")]
+    kind: String,
+    color: String,
+    spacing: f64,
+    width: f64,
+    #[serde(default = "default_angle")]
+    angle: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct TextDocument {
+    fill_color: String,
+    size: Stops,
+    #[serde(default = "default_weight")]
+    weight: usize,
+    #[serde(default)]
+    priority: i32,
+    halo_color: Option<String>,
+    #[serde(default = "default_zero")]
+    halo_width: Stops,
+    #[serde(default = "default_opacity")]
+    opacity: Stops,
+    #[serde(default = "default_zero")]
+    repeat_distance: Stops,
+}
+
+// Every numeric property is a number, or zoom stops like { "base": 1.4, "stops": [[10, 1], [18, 12]] }
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StopsDocument {
+    Value(f64),
+    Stops {
+        #[serde(default = "default_base")]
+        base: f64,
+        stops: Vec<(f64, f64)>,
+    },
+}
+
+impl<'de> Deserialize<'de> for Stops {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Stops, D::Error> {
+        match StopsDocument::deserialize(deserializer)? {
+            StopsDocument::Value(value) => Ok(Stops::new(value)),
+            StopsDocument::Stops { base, stops } => Ok(Stops::exponential(base, stops)),
+        }
+    }
+}
+
+fn default_base() -> f64 {
+    1.0
+}
+
+fn default_marker() -> String {
+    String::from("circle")
+}
+
+fn default_weight() -> usize {
+    400
+}
+
+fn default_opacity() -> Stops {
+    Stops::new(1.0)
+}
+
+fn default_zero() -> Stops {
+    Stops::new(0.0)
+}
+
+fn default_angle() -> f64 {
+    45.0
+}
+
+impl PatternDocument {
+    fn into_pattern(self) -> Result<Pattern, String> {
+        let kind = match self.kind.as_str() {
+            "hatch" => PatternKind::Hatch,
+            "cross" => PatternKind::Cross,
+            "dots" => PatternKind::Dots,
+            kind => return Err(format!("unknown pattern '{}'", kind)),
+        };
+        Ok(Pattern {
+            angle: self.angle,
+            ..Pattern::new(kind, &self.color, self.spacing, self.width)
+        })
+    }
+}
+
+impl RuleDocument {
+    fn into_rule(self) -> Result<Rule, String> {
+        let mut rule = Rule::new();
+        if let Some(filter) = self.filter {
+            rule.filter = Some(Expr::parse(&filter)?);
+        }
+        if let Some(min_zoom) = self.min_zoom {
+            rule.min_zoom = min_zoom;
+        }
+        if let Some(max_zoom) = self.max_zoom {
+            rule.max_zoom = max_zoom;
+        }
+        if let Some(label) = self.label {
+            rule.label = label;
+        }
+        if let Some(label_min_zoom) = self.label_min_zoom {
+            rule.label_min_zoom = label_min_zoom;
+        }
+
+        if let Some(point) = self.point {
+            let marker = match (&point.icon, point.marker.as_str()) {
+                (Some(icon), _) => Marker::icon(icon).map_err(|err| format!("{}: {}", icon, err))?,
+                (None, "circle") => Marker::Circle,
+                (None, "square") => Marker::Square,
+                (None, marker) => return Err(format!("unknown marker '{}'", marker)),
+            };
+            rule.point = Some(PointProps {
+                size: point.size,
+                rotation: point.rotation,
+                border_width: point.border_width,
+                ..PointProps::new(marker, 0, &point.fill_color, &point.border_color, 0, point.priority)
+            });
+        }
+
+        if let Some(polyline) = self.polyline {
+            let mut props = PolylineProps::new(&polyline.color, 0, polyline.priority);
+            props.width = polyline.width;
+            props.dash = polyline.dash;
+            props.opacity = polyline.opacity;
+            if let Some(cap) = polyline.cap {
+                props.cap = cap;
+            }
+            if let Some(join) = polyline.join {
+                props.join = join;
+            }
+            if let Some(casing_color) = polyline.casing_color {
+                props.casing_color = casing_color;
+            }
+            props.casing_width = polyline.casing_width;
+            if let Some(casing_priority) = polyline.casing_priority {
+                props.casing_priority = casing_priority;
+            }
+            rule.polyline = Some(props);
+        }
+
+        if let Some(polygon) = self.polygon {
+            let mut props = PolygonProps::new(
+                &polygon.fill_color,
+                &polygon.border_color,
+                0,
+                polygon.fill_priority,
+                polygon.border_priority
+            );
+            props.border_width = polygon.border_width;
+            props.fill_opacity = polygon.fill_opacity;
+            props.border_opacity = polygon.border_opacity;
+            props.border_dash = polygon.border_dash;
+            if let Some(pattern) = polygon.pattern {
+                props.pattern = Some(pattern.into_pattern()?);
+            }
+            rule.polygon = Some(props);
+        }
+
+        if let Some(text) = self.text {
+            let mut props = TextProps::new(&text.fill_color, 0, text.weight, text.priority);
+            props.size = text.size;
+            if let Some(halo_color) = text.halo_color {
+                props.halo_color = halo_color;
+            }
+            props.halo_width = text.halo_width;
+            props.opacity = text.opacity;
+            props.repeat_distance = text.repeat_distance;
+            rule.text = Some(props);
+        }
+
+        Ok(rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile::DEFAULT_HALO_COLOR;
+
+    #[test]
+    fn test_parse() {
+        let style = Style::parse(r##"{
+            "name": "test",
+            "layers": [
+                {
+                    "id": "motorway",
+                    "source": "road",
+                    "minzoom": 8,
+                    "datum": "gcj02",
+                    "rules": [
+                        {
+                            "filter": "fclass == 'motorway'",
+                            "minzoom": 11,
+                            "label": "ref",
+                            "polyline": {
+                                "color": "#e8a04d",
+                                "width": { "base": 1.5, "stops": [[10, 2], [18, 12]] },
+                                "dash": [4, 2],
+                                "cap": "round",
+                                "casing-color": "#a06020",
+                                "casing-width": 1,
+                                "casing-priority": 5
+                            },
+                            "text": { "fill-color": "#000000", "size": { "stops": [[12, 12], [16, 20]] }, "halo-width": 2 }
+                        }
+                    ]
+                },
+                {
+                    "id": "water",
+                    "rules": [
+                        {
+                            "polygon": {
+                                "fill-color": "#90daee",
+                                "border-color": "#90daee",
+                                "border-width": 1,
+                                "fill-opacity": 0.8,
+                                "border-dash": [2, 2],
+                                "pattern": { "type": "dots", "color": "#4080c0", "spacing": 6, "width": 1.5 }
+                            }
+                        },
+                        { "point": { "marker": "square", "size": 6, "fill-color": "#90daee", "border-color": "#ffffff" } }
+                    ]
+                }
+            ]
+        }"##).unwrap();
+
+        assert_eq!(style.name, "test");
+        assert_eq!(style.layers.len(), 2);
+        assert_eq!(style.layers[0].source, "road");
+        assert_eq!(style.layers[1].source, "water");
+        assert_eq!(style.layers[0].datum, Datum::GCJ02);
+        assert_eq!(style.layers[1].datum, Datum::WGS84);
+        assert_eq!(style.layers[0].min_zoom, 8);
+        assert_eq!(style.layers[0].max_zoom, u64::MAX);
+
+        let rule = &style.layers[0].rules[0];
+        assert!(rule.filter.is_some());
+        assert_eq!(rule.min_zoom, 11);
+        assert_eq!(rule.label, "ref");
+        let polyline = rule.polyline.as_ref().unwrap();
+        assert_eq!(polyline.dash, vec![4.0, 2.0]);
+        assert_eq!(polyline.width, Stops::exponential(1.5, vec![(10.0, 2.0), (18.0, 12.0)]));
+        assert_eq!(polyline.cap, "round");
+        assert_eq!(polyline.join, "miter");
+        assert_eq!(polyline.casing_width.at(14.0), 1.0);
+        assert_eq!(polyline.casing_priority, 5);
+        let text = rule.text.as_ref().unwrap();
+        assert_eq!(text.weight, 400);
+        assert_eq!(text.size.at(14.0), 16.0);
+        assert_eq!(text.halo_width, Stops::new(2.0));
+        assert_eq!(text.halo_color, DEFAULT_HALO_COLOR);
+
+        let polygon = style.layers[1].rules[0].polygon.as_ref().unwrap();
+        assert_eq!(polygon.fill_opacity, Stops::new(0.8));
+        assert_eq!(polygon.border_opacity, Stops::new(1.0));
+        assert_eq!(polygon.border_dash, vec![2.0, 2.0]);
+        assert_eq!(polygon.pattern, Some(Pattern { angle: 45.0, ..Pattern::new(PatternKind::Dots, "#4080c0", 6.0, 1.5) }));
+
+        assert!(matches!(style.layers[1].rules[1].point.as_ref().unwrap().marker, Marker::Square));
+    }
+
+    #[test]
+    fn test_parse_background() {
+        let style = Style::parse(r##"{
+            "background": {
+                "color": "#90daee",
+                "pattern": { "type": "hatch", "color": "#80c0e0", "spacing": 8, "width": 1 },
+                "base": { "source": "land", "color": "#f5f0e5" }
+            },
+            "layers": [{ "id": "road", "rules": [] }]
+        }"##).unwrap();
+
+        let background = style.background.unwrap();
+        assert_eq!(background.color, "#90daee");
+        assert_eq!(background.pattern.unwrap().kind, PatternKind::Hatch);
+        assert_eq!(style.layers.len(), 2);
+        assert_eq!(style.layers[0].source, "land");
+        let base = style.layers[0].rules[0].polygon.as_ref().unwrap();
+        assert_eq!(base.fill_color, "#f5f0e5");
+        assert_eq!(base.fill_priority, BASE_PRIORITY);
+
+        let style = Style::parse(r#"{ "layers": [] }"#).unwrap();
+        assert!(style.background.is_none());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Style::parse("{").is_err());
+        assert!(Style::parse(r#"{ "layers": [{ "id": "a", "rules": [{ "filter": "a ==" }] }] }"#).is_err());
+        assert!(Style::parse(r#"{ "layers": [{ "id": "a", "rules": [{ "colour": "red" }] }] }"#).is_err());
+        assert!(Style::parse(r#"{ "layers": [{ "id": "a", "rules": [{ "polygon": {
+            "fill-color": "red", "border-color": "red", "pattern": { "type": "stripes", "color": "red", "spacing": 4, "width": 1 }
+        } }] }] }"#).is_err());
+        assert!(Style::parse(r#"{ "layers": [{ "id": "a", "datum": "nad83", "rules": [] }] }"#).is_err());
+        assert!(Style::load("styles/missing.json").is_err());
+    }
+
+    #[test]
+    fn test_load() {
+        for name in ["light", "dark", "contrast"] {
+            let style = Style::load(&format!("styles/{}.json", name)).unwrap();
+            assert_eq!(style.name, name);
+            assert!(style.layers.iter().any(|layer| layer.source == "road"));
+        }
+    }
+}
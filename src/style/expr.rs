@@ -0,0 +1,267 @@
+use std::{cmp::Ordering, collections::HashMap};
+use crate::geography::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+// A filter over the attributes of a feature, e.g. `fclass == "motorway" && population > 1e6`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Value),
+    Field(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(CompareOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    String(String),
+    Ident(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 13] = ["==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "(", ")", "-", "="];
+
+#[allow(dead_code)]
+impl Expr {
+    // Operators from the loosest: `||`, `&&`, comparisons, `!`
+    // `and`, `or` and `not` are accepted as keywords, `=` as `==`
+    pub fn parse(text: &str) -> Result<Expr, String> {
+        let tokens = Expr::tokenize(text)?;
+        let mut pos = 0;
+        let expr = Expr::parse_or(&tokens, &mut pos)?;
+        if pos < tokens.len() {
+            return Err(format!("unexpected {:?} in '{}'", tokens[pos], text));
+        }
+        Ok(expr)
+    }
+
+    pub fn eval(&self, attrs: &HashMap<String, Value>) -> Value {
+        match self {
+            Expr::Literal(value) => value.clone(),
+            Expr::Field(name) => attrs.get(name).cloned().unwrap_or(Value::Null),
+            Expr::Not(expr) => Value::Bool(!expr.eval(attrs).is_truthy()),
+            Expr::And(left, right) => Value::Bool(left.eval(attrs).is_truthy() && right.eval(attrs).is_truthy()),
+            Expr::Or(left, right) => Value::Bool(left.eval(attrs).is_truthy() || right.eval(attrs).is_truthy()),
+            Expr::Compare(op, left, right) => {
+                let ordering = Expr::compare(&left.eval(attrs), &right.eval(attrs));
+                Value::Bool(match op {
+                    CompareOp::Eq => ordering == Some(Ordering::Equal),
+                    CompareOp::Ne => ordering != Some(Ordering::Equal),
+                    CompareOp::Lt => ordering == Some(Ordering::Less),
+                    CompareOp::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+                    CompareOp::Gt => ordering == Some(Ordering::Greater),
+                    CompareOp::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+                })
+            },
+        }
+    }
+
+    pub fn matches(&self, attrs: &HashMap<String, Value>) -> bool {
+        self.eval(attrs).is_truthy()
+    }
+
+    // Numbers compare with numbers or numeric strings, other values only with the same kind
+    fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+        match (left, right) {
+            (Value::Null, Value::Null) => Some(Ordering::Equal),
+            (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            (Value::Number(_), _) | (_, Value::Number(_)) => left.as_number()?.partial_cmp(&right.as_number()?),
+            _ => None,
+        }
+    }
+
+    fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            if c.is_whitespace() {
+                i += 1;
+            } else if c == '"' || c == '\'' {
+                let start = i + 1;
+                i = start;
+                while i < chars.len() && chars[i] != c {
+                    i += 1;
+                }
+                if i == chars.len() {
+                    return Err(format!("unterminated string in '{}'", text));
+                }
+                tokens.push(Token::String(chars[start..i].iter().collect()));
+                i += 1;
+            } else if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit())) {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                    i += 1;
+                    if i < chars.len() && (chars[i] == '+' || chars[i] == '-') {
+                        i += 1;
+                    }
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+                let number: String = chars[start..i].iter().collect();
+                let number = number.parse().map_err(|_| format!("invalid number '{}' in '{}'", number, text))?;
+                tokens.push(Token::Number(number));
+            } else if c.is_alphabetic() || c == '_' {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            } else {
+                let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+                let symbol = SYMBOLS.iter().find(|symbol| rest.starts_with(**symbol));
+                match symbol {
+                    Some(symbol) => {
+                        tokens.push(Token::Symbol(symbol));
+                        i += symbol.len();
+                    },
+                    None => return Err(format!("unexpected '{}' in '{}'", c, text)),
+                }
+            }
+        }
+        Ok(tokens)
+    }
+
+    fn is_keyword(token: Option<&Token>, symbol: &str, keyword: &str) -> bool {
+        match token {
+            Some(Token::Symbol(s)) => *s == symbol,
+            Some(Token::Ident(s)) => s.eq_ignore_ascii_case(keyword),
+            _ => false,
+        }
+    }
+
+    fn parse_or(tokens: &[Token], pos: &mut usize) -> Result<Expr, String> {
+        let mut expr = Expr::parse_and(tokens, pos)?;
+        while Expr::is_keyword(tokens.get(*pos), "||", "or") {
+            *pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(Expr::parse_and(tokens, pos)?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(tokens: &[Token], pos: &mut usize) -> Result<Expr, String> {
+        let mut expr = Expr::parse_compare(tokens, pos)?;
+        while Expr::is_keyword(tokens.get(*pos), "&&", "and") {
+            *pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(Expr::parse_compare(tokens, pos)?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_compare(tokens: &[Token], pos: &mut usize) -> Result<Expr, String> {
+        let left = Expr::parse_unary(tokens, pos)?;
+        let op = match tokens.get(*pos) {
+            Some(Token::Symbol("==")) | Some(Token::Symbol("=")) => CompareOp::Eq,
+            Some(Token::Symbol("!=")) => CompareOp::Ne,
+            Some(Token::Symbol("<")) => CompareOp::Lt,
+            Some(Token::Symbol("<=")) => CompareOp::Le,
+            Some(Token::Symbol(">")) => CompareOp::Gt,
+            Some(Token::Symbol(">=")) => CompareOp::Ge,
+            _ => return Ok(left),
+        };
+        *pos += 1;
+        let right = Expr::parse_unary(tokens, pos)?;
+        Ok(Expr::Compare(op, Box::new(left), Box::new(right)))
+    }
+
+    fn parse_unary(tokens: &[Token], pos: &mut usize) -> Result<Expr, String> {
+        let token = tokens.get(*pos).ok_or_else(|| String::from("unexpected end of expression"))?;
+        *pos += 1;
+        if Expr::is_keyword(Some(token), "!", "not") {
+            return Ok(Expr::Not(Box::new(Expr::parse_unary(tokens, pos)?)));
+        }
+
+        match token {
+            Token::Number(value) => Ok(Expr::Literal(Value::Number(*value))),
+            Token::String(value) => Ok(Expr::Literal(Value::String(value.clone()))),
+            Token::Symbol("-") => match tokens.get(*pos) {
+                Some(Token::Number(value)) => {
+                    *pos += 1;
+                    Ok(Expr::Literal(Value::Number(-value)))
+                },
+                _ => Err(String::from("expected a number after '-'")),
+            },
+            Token::Symbol("(") => {
+                let expr = Expr::parse_or(tokens, pos)?;
+                if tokens.get(*pos) != Some(&Token::Symbol(")")) {
+                    return Err(String::from("expected ')'"));
+                }
+                *pos += 1;
+                Ok(expr)
+            },
+            Token::Ident(name) => match name.as_str() {
+                "true" => Ok(Expr::Literal(Value::Bool(true))),
+                "false" => Ok(Expr::Literal(Value::Bool(false))),
+                "null" => Ok(Expr::Literal(Value::Null)),
+                _ => Ok(Expr::Field(name.clone())),
+            },
+            Token::Symbol(symbol) => Err(format!("unexpected '{}'", symbol)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attrs() -> HashMap<String, Value> {
+        let mut attrs = HashMap::new();
+        attrs.insert(String::from("fclass"), Value::String(String::from("motorway")));
+        attrs.insert(String::from("population"), Value::Number(2.5e6));
+        attrs.insert(String::from("lanes"), Value::String(String::from("4")));
+        attrs.insert(String::from("bridge"), Value::Bool(false));
+        attrs
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Expr::parse(r#"fclass == "motorway""#).unwrap(),
+            Expr::Compare(
+                CompareOp::Eq,
+                Box::new(Expr::Field(String::from("fclass"))),
+                Box::new(Expr::Literal(Value::String(String::from("motorway"))))
+            )
+        );
+        assert!(Expr::parse("fclass ==").is_err());
+        assert!(Expr::parse("(a == 1").is_err());
+        assert!(Expr::parse("a == 'b").is_err());
+        assert!(Expr::parse("a == 1 2").is_err());
+        assert!(Expr::parse("a # 1").is_err());
+    }
+
+    #[test]
+    fn test_eval() {
+        let attrs = attrs();
+        let matches = |text: &str| Expr::parse(text).unwrap().matches(&attrs);
+        assert!(matches(r#"fclass == "motorway""#));
+        assert!(matches("fclass = 'motorway'"));
+        assert!(!matches(r#"fclass != "motorway""#));
+        assert!(matches("population > 1e6"));
+        assert!(!matches("population <= 1e6"));
+        assert!(matches("lanes >= 4 && lanes < 6"));
+        assert!(matches("population > -1"));
+        assert!(matches(r#"fclass == "primary" || population >= 2.5e6"#));
+        assert!(matches("not bridge and (fclass == 'motorway' or fclass == 'trunk')"));
+        assert!(matches("!missing && missing == null"));
+        assert!(!matches("fclass > 1"));
+        assert!(matches("fclass"));
+    }
+}
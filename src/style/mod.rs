@@ -0,0 +1,7 @@
+mod document;
+mod expr;
+mod rule;
+
+pub use document::*;
+pub use expr::*;
+pub use rule::*;
\ No newline at end of file
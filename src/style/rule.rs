@@ -0,0 +1,137 @@
+use crate::{geography::Info, tile::{Datum, PointProps, PolygonProps, PolylineProps, TextProps}};
+use super::Expr;
+
+// How the features matching a filter are drawn within a zoom range.
+// A feature is not drawn as a geometry whose props are missing, and not labeled without text props.
+#[allow(dead_code)]
+pub struct Rule {
+    pub filter: Option<Expr>,
+    // Inclusive on both ends
+    pub min_zoom: u64,
+    pub max_zoom: u64,
+    pub point: Option<PointProps>,
+    pub polyline: Option<PolylineProps>,
+    pub polygon: Option<PolygonProps>,
+    pub text: Option<TextProps>,
+    // Attribute drawn as the label
+    pub label: String,
+    // Labels are only drawn from this zoom
+    pub label_min_zoom: u64,
+}
+
+#[allow(dead_code)]
+impl Rule {
+    pub fn new() -> Rule {
+        Rule {
+            filter: None,
+            min_zoom: 0,
+            max_zoom: u64::MAX,
+            point: None,
+            polyline: None,
+            polygon: None,
+            text: None,
+            label: String::from("name"),
+            label_min_zoom: 0,
+        }
+    }
+
+    pub fn matches(&self, info: &Info, z: u64) -> bool {
+        if z < self.min_zoom || z > self.max_zoom {
+            return false;
+        }
+
+        match &self.filter {
+            Some(filter) => filter.matches(&info.attrs),
+            None => true,
+        }
+    }
+
+    pub fn label(&self, info: &Info) -> String {
+        match info.attrs.get(&self.label) {
+            Some(value) => value.to_string(),
+            None => String::new(),
+        }
+    }
+}
+
+// The rules of a layer, the first one matching a feature is used
+#[allow(dead_code)]
+pub struct Layer {
+    pub name: String,
+    // Name of the data drawn by the layer
+    pub source: String,
+    // The layer is hidden outside of the zooms, inclusive on both ends
+    pub min_zoom: u64,
+    pub max_zoom: u64,
+    // Datum of the coordinates of the source, shifted to the datum of the tile when drawn
+    pub datum: Datum,
+    pub rules: Vec<Rule>,
+}
+
+#[allow(dead_code)]
+impl Layer {
+    pub fn new(name: &str) -> Layer {
+        Layer { name: String::from(name), source: String::from(name), min_zoom: 0, max_zoom: u64::MAX, datum: Datum::default(), rules: Vec::new() }
+    }
+
+    pub fn is_visible(&self, z: u64) -> bool {
+        z >= self.min_zoom && z <= self.max_zoom
+    }
+
+    pub fn add(&mut self, rule: Rule) {
+        self.rules.push(rule);
+    }
+
+    pub fn find(&self, info: &Info, z: u64) -> Option<&Rule> {
+        if !self.is_visible(z) {
+            return None;
+        }
+        self.rules.iter().find(|rule| rule.matches(info, z))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::{geography::Value, geometry::Rectangle};
+    use super::*;
+
+    fn info(fclass: &str, name: &str) -> Info {
+        let mut attrs = HashMap::new();
+        attrs.insert(String::from("fclass"), Value::String(String::from(fclass)));
+        attrs.insert(String::from("name"), Value::String(String::from(name)));
+        attrs.insert(String::from("ref"), Value::String(String::from("G4")));
+        Info { name: String::from(name), rect: Rectangle::uninitialized(), anchor: None, attrs }
+    }
+
+    #[test]
+    fn test_layer() {
+        let mut layer = Layer::new("road");
+        layer.add(Rule {
+            filter: Some(Expr::parse(r#"fclass == "motorway""#).unwrap()),
+            polyline: Some(PolylineProps::new("orange", 4, 10)),
+            label: String::from("ref"),
+            ..Rule::new()
+        });
+        layer.add(Rule {
+            min_zoom: 12,
+            polyline: Some(PolylineProps::new("grey", 1, 10)),
+            ..Rule::new()
+        });
+
+        let motorway = info("motorway", "Jingzhu");
+        let rule = layer.find(&motorway, 5).unwrap();
+        assert_eq!(rule.polyline.as_ref().unwrap().color, "orange");
+        assert_eq!(rule.label(&motorway), "G4");
+
+        let residential = info("residential", "Jiefang");
+        assert!(layer.find(&residential, 11).is_none());
+        let rule = layer.find(&residential, 12).unwrap();
+        assert_eq!(rule.polyline.as_ref().unwrap().color, "grey");
+        assert_eq!(rule.label(&residential), "Jiefang");
+
+        layer.max_zoom = 14;
+        assert!(layer.is_visible(14));
+        assert!(layer.find(&motorway, 15).is_none());
+    }
+}
@@ -0,0 +1,175 @@
+use std::f64::consts::PI;
+
+// Geodetic datums of the Chinese basemaps, GCJ-02 (Amap, Tencent) and BD-09 (Baidu) are
+// obfuscated offsets of WGS-84 of a few hundred metres, only applied within China
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Datum {
+    WGS84,
+    GCJ02,
+    BD09,
+}
+
+// Ellipsoid of the GCJ-02 offset (Krasovsky 1940)
+const GCJ_A: f64 = 6378245.0;
+const GCJ_EE: f64 = 0.006_693_421_622_965_943;
+const BD_X_PI: f64 = PI * 3000.0 / 180.0;
+// The offsets have no closed form inverse, they are inverted by iterations until this precision in degrees
+const INVERSE_PRECISION: f64 = 1e-10;
+const INVERSE_ITERATIONS: usize = 30;
+
+#[allow(dead_code)]
+impl Datum {
+    pub fn default() -> Datum {
+        Datum::WGS84
+    }
+
+    pub fn parse(name: &str) -> Option<Datum> {
+        match name.to_lowercase().as_str() {
+            "wgs84" | "wgs-84" => Some(Datum::WGS84),
+            "gcj02" | "gcj-02" => Some(Datum::GCJ02),
+            "bd09" | "bd-09" => Some(Datum::BD09),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Datum::WGS84 => "wgs84",
+            Datum::GCJ02 => "gcj02",
+            Datum::BD09 => "bd09",
+        }
+    }
+
+    // Shifts a WGS-84 longitude/latitude to this datum
+    pub fn shift(self, longitude_latitude: (f64, f64)) -> (f64, f64) {
+        match self {
+            Datum::WGS84 => longitude_latitude,
+            Datum::GCJ02 => wgs84_to_gcj02(longitude_latitude),
+            Datum::BD09 => gcj02_to_bd09(wgs84_to_gcj02(longitude_latitude)),
+        }
+    }
+
+    // Shifts a longitude/latitude of this datum back to WGS-84
+    pub fn unshift(self, longitude_latitude: (f64, f64)) -> (f64, f64) {
+        match self {
+            Datum::WGS84 => longitude_latitude,
+            Datum::GCJ02 => gcj02_to_wgs84(longitude_latitude),
+            Datum::BD09 => gcj02_to_wgs84(bd09_to_gcj02(longitude_latitude)),
+        }
+    }
+
+    pub fn convert(self, to: Datum, longitude_latitude: (f64, f64)) -> (f64, f64) {
+        if self == to {
+            return longitude_latitude;
+        }
+        to.shift(self.unshift(longitude_latitude))
+    }
+}
+
+// GCJ-02 leaves the coordinates outside of this rough box around China untouched
+fn in_china((longitude, latitude): (f64, f64)) -> bool {
+    (72.004..=137.8347).contains(&longitude) && (0.8293..=55.8271).contains(&latitude)
+}
+
+fn gcj02_offset_latitude(x: f64, y: f64) -> f64 {
+    let mut offset = -100.0 + 2.0 * x + 3.0 * y + 0.2 * y * y + 0.1 * x * y + 0.2 * x.abs().sqrt();
+    offset += (20.0 * (6.0 * x * PI).sin() + 20.0 * (2.0 * x * PI).sin()) * 2.0 / 3.0;
+    offset += (20.0 * (y * PI).sin() + 40.0 * (y / 3.0 * PI).sin()) * 2.0 / 3.0;
+    offset += (160.0 * (y / 12.0 * PI).sin() + 320.0 * (y * PI / 30.0).sin()) * 2.0 / 3.0;
+    offset
+}
+
+fn gcj02_offset_longitude(x: f64, y: f64) -> f64 {
+    let mut offset = 300.0 + x + 2.0 * y + 0.1 * x * x + 0.1 * x * y + 0.1 * x.abs().sqrt();
+    offset += (20.0 * (6.0 * x * PI).sin() + 20.0 * (2.0 * x * PI).sin()) * 2.0 / 3.0;
+    offset += (20.0 * (x * PI).sin() + 40.0 * (x / 3.0 * PI).sin()) * 2.0 / 3.0;
+    offset += (150.0 * (x / 12.0 * PI).sin() + 300.0 * (x / 30.0 * PI).sin()) * 2.0 / 3.0;
+    offset
+}
+
+fn wgs84_to_gcj02((longitude, latitude): (f64, f64)) -> (f64, f64) {
+    if !in_china((longitude, latitude)) {
+        return (longitude, latitude);
+    }
+
+    let (x, y) = (longitude - 105.0, latitude - 35.0);
+    let radians = latitude.to_radians();
+    let magic = 1.0 - GCJ_EE * radians.sin() * radians.sin();
+    let sqrt_magic = magic.sqrt();
+    let d_latitude = gcj02_offset_latitude(x, y) * 180.0 / ((GCJ_A * (1.0 - GCJ_EE)) / (magic * sqrt_magic) * PI);
+    let d_longitude = gcj02_offset_longitude(x, y) * 180.0 / (GCJ_A / sqrt_magic * radians.cos() * PI);
+    (longitude + d_longitude, latitude + d_latitude)
+}
+
+// Finds the point that forward moves to target, starting from a guess, the offsets are small and smooth enough
+// for the error of forward at the guess to be a good correction of the guess
+fn invert(forward: fn((f64, f64)) -> (f64, f64), target: (f64, f64), guess: (f64, f64)) -> (f64, f64) {
+    let mut point = guess;
+    for _ in 0..INVERSE_ITERATIONS {
+        let moved = forward(point);
+        let (dx, dy) = (moved.0 - target.0, moved.1 - target.1);
+        point = (point.0 - dx, point.1 - dy);
+        if dx.abs() < INVERSE_PRECISION && dy.abs() < INVERSE_PRECISION {
+            break;
+        }
+    }
+    point
+}
+
+fn gcj02_to_wgs84(gcj: (f64, f64)) -> (f64, f64) {
+    invert(wgs84_to_gcj02, gcj, gcj)
+}
+
+fn gcj02_to_bd09((longitude, latitude): (f64, f64)) -> (f64, f64) {
+    let z = (longitude * longitude + latitude * latitude).sqrt() + 0.00002 * (latitude * BD_X_PI).sin();
+    let theta = latitude.atan2(longitude) + 0.000003 * (longitude * BD_X_PI).cos();
+    (z * theta.cos() + 0.0065, z * theta.sin() + 0.006)
+}
+
+// The usual approximate inverse, refined to the precision of the GCJ-02 inverse
+fn bd09_to_gcj02(bd: (f64, f64)) -> (f64, f64) {
+    let (x, y) = (bd.0 - 0.0065, bd.1 - 0.006);
+    let z = (x * x + y * y).sqrt() - 0.00002 * (y * BD_X_PI).sin();
+    let theta = y.atan2(x) - 0.000003 * (x * BD_X_PI).cos();
+    invert(gcj02_to_bd09, bd, (z * theta.cos(), z * theta.sin()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+        ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+    }
+
+    #[test]
+    fn test_datum() {
+        let wuhan = (114.40, 30.67);
+
+        // Amap and Baidu are a few hundred metres off in Wuhan
+        let gcj = Datum::GCJ02.shift(wuhan);
+        assert!(gcj.0 > wuhan.0 && gcj.1 < wuhan.1);
+        assert!((0.001..0.01).contains(&distance(gcj, wuhan)));
+        let bd = Datum::BD09.shift(wuhan);
+        assert!((0.005..0.02).contains(&distance(bd, gcj)));
+
+        assert!(distance(Datum::GCJ02.unshift(gcj), wuhan) < 1e-8);
+        assert!(distance(Datum::BD09.unshift(bd), wuhan) < 1e-8);
+        assert!(distance(Datum::GCJ02.convert(Datum::BD09, gcj), bd) < 1e-8);
+        assert!(distance(Datum::BD09.convert(Datum::GCJ02, bd), gcj) < 1e-8);
+        assert_eq!(Datum::WGS84.convert(Datum::WGS84, wuhan), wuhan);
+
+        // No offset outside of China
+        let paris = (2.35, 48.85);
+        assert_eq!(Datum::GCJ02.shift(paris), paris);
+        assert_eq!(Datum::GCJ02.unshift(paris), paris);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Datum::parse("GCJ-02"), Some(Datum::GCJ02));
+        assert_eq!(Datum::parse("bd09"), Some(Datum::BD09));
+        assert_eq!(Datum::parse(Datum::WGS84.name()), Some(Datum::WGS84));
+        assert_eq!(Datum::parse("nad83"), None);
+    }
+}
@@ -0,0 +1,98 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}};
+use resvg::usvg::{fontdb::{Database as FontDatabase, Family, Query, Stretch, Style, Weight, ID}, Options};
+
+// Advance used when no font is able to render a char, in em
+const FALLBACK_ADVANCE: f64 = 0.5;
+
+#[allow(dead_code)]
+pub struct FontMetrics {
+    fontdb: Arc<FontDatabase>,
+    family: String,
+    advances: Mutex<HashMap<(char, usize), f64>>,
+}
+
+#[allow(dead_code)]
+impl FontMetrics {
+    pub fn new(fontdb: Arc<FontDatabase>) -> FontMetrics {
+        FontMetrics {
+            fontdb,
+            family: Options::default().font_family,
+            advances: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn text_width(&self, text: &str, size: f64, weight: usize) -> f64 {
+        let mut advances = self.advances.lock().unwrap();
+        let mut width = 0.0;
+        for c in text.chars() {
+            let advance = *advances.entry((c, weight)).or_insert_with(|| self.advance(c, weight));
+            width += advance * size;
+        }
+        width
+    }
+
+    // Resolves the font the same way usvg does: the default family, then the serif family,
+    // then the first font with the same style that has the char.
+    // Any font with the char is accepted when neither family is installed.
+    fn advance(&self, c: char, weight: usize) -> f64 {
+        let query = Query {
+            families: &[Family::Name(&self.family), Family::Serif],
+            weight: Weight(weight as u16),
+            stretch: Stretch::Normal,
+            style: Style::Normal,
+        };
+
+        let base = self.fontdb.query(&query);
+        if let Some(advance) = base.and_then(|base| self.glyph_advance(base, c)) {
+            return advance;
+        }
+
+        let base_face = base.and_then(|base| self.fontdb.face(base));
+        for face in self.fontdb.faces() {
+            if let Some(base_face) = base_face {
+                if face.id == base_face.id {
+                    continue;
+                }
+
+                if base_face.style != face.style && base_face.weight != face.weight && base_face.stretch != face.stretch {
+                    continue;
+                }
+            }
+
+            if let Some(advance) = self.glyph_advance(face.id, c) {
+                return advance;
+            }
+        }
+
+        FALLBACK_ADVANCE
+    }
+
+    fn glyph_advance(&self, id: ID, c: char) -> Option<f64> {
+        self.fontdb.with_face_data(id, |data, index| {
+            let face = ttf_parser::Face::parse(data, index).ok()?;
+            let glyph = face.glyph_index(c)?;
+            let advance = face.glyph_hor_advance(glyph)?;
+            Some(advance as f64 / face.units_per_em() as f64)
+        })?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_width() {
+        let metrics = FontMetrics::new(Arc::new(FontDatabase::new()));
+        assert_eq!(metrics.text_width("", 20.0, 400), 0.0);
+        assert_eq!(metrics.text_width("abcd", 20.0, 400), 40.0);
+
+        let mut fontdb = FontDatabase::new();
+        fontdb.load_system_fonts();
+        let metrics = FontMetrics::new(Arc::new(fontdb));
+        let narrow = metrics.text_width("iiii", 20.0, 400);
+        let wide = metrics.text_width("WWWW", 20.0, 400);
+        assert!(narrow < wide);
+        assert_eq!(metrics.text_width("WWWW", 40.0, 400), wide * 2.0);
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{geometry::Rectangle, xml::XmlNode};
+
+#[allow(dead_code)]
+pub struct Placement {
+    pub boxes: Vec<Rectangle>,
+    pub node: XmlNode,
+}
+
+#[allow(dead_code)]
+impl Placement {
+    pub fn new(boxes: Vec<Rectangle>, node: XmlNode) -> Placement {
+        Placement { boxes, node }
+    }
+
+    fn is_inside(&self, bounds: &Rectangle) -> bool {
+        self.boxes.iter().all(|rect| {
+            rect.min.x >= bounds.min.x && rect.min.y >= bounds.min.y && rect.max.x <= bounds.max.x && rect.max.y <= bounds.max.y
+        })
+    }
+
+    // Boxes that only touch do not collide
+    fn is_collide(&self, placed: &[Rectangle]) -> bool {
+        self.boxes.iter().any(|a| placed.iter().any(|b| {
+            a.min.x < b.max.x && b.min.x < a.max.x && a.min.y < b.max.y && b.min.y < a.max.y
+        }))
+    }
+}
+
+// A label with its candidate placements, in order of preference
+#[allow(dead_code)]
+pub struct Label {
+    pub priority: i32,
+    pub placements: Vec<Placement>,
+}
+
+#[allow(dead_code)]
+impl Label {
+    pub fn new(priority: i32) -> Label {
+        Label { priority, placements: Vec::new() }
+    }
+
+    pub fn add_placement(&mut self, placement: Placement) {
+        self.placements.push(placement);
+    }
+}
+
+#[allow(dead_code)]
+pub struct LabelPlacer {
+    bounds: Rectangle,
+    labels: Vec<Label>,
+    // Boxes taken by symbols, labels are kept off them
+    obstacles: Vec<Rectangle>,
+}
+
+#[allow(dead_code)]
+impl LabelPlacer {
+    pub fn new(bounds: &Rectangle) -> LabelPlacer {
+        LabelPlacer { bounds: bounds.clone(), labels: Vec::new(), obstacles: Vec::new() }
+    }
+
+    pub fn add(&mut self, label: Label) {
+        self.labels.push(label);
+    }
+
+    pub fn add_obstacle(&mut self, rect: Rectangle) {
+        self.obstacles.push(rect);
+    }
+
+    // Places labels from the highest priority down, labels added earlier win ties.
+    // Each label takes its first placement that is inside the bounds and does not overlap the placed ones, or is dropped.
+    pub fn place(&mut self) -> Vec<XmlNode> {
+        let mut labels = std::mem::take(&mut self.labels);
+        labels.sort_by_key(|label| std::cmp::Reverse(label.priority));
+
+        let mut placed = std::mem::take(&mut self.obstacles);
+        let mut nodes = Vec::new();
+        for label in labels {
+            let placement = label.placements.into_iter().find(|placement| {
+                placement.is_inside(&self.bounds) && !placement.is_collide(&placed)
+            });
+            if let Some(placement) = placement {
+                placed.extend(placement.boxes);
+                nodes.push(placement.node);
+            }
+        }
+        nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::Point;
+    use super::*;
+
+    fn placement(text: &str, x: f64, y: f64) -> Placement {
+        let rect = Rectangle::new(&Point { x, y }, &Point { x: x + 10.0, y: y + 10.0 });
+        Placement::new(vec![rect], XmlNode::new("text", text))
+    }
+
+    #[test]
+    fn test_place() {
+        let bounds = Rectangle::new(&Point { x: 0.0, y: 0.0 }, &Point { x: 100.0, y: 100.0 });
+        let mut placer = LabelPlacer::new(&bounds);
+
+        let mut label = Label::new(10);
+        label.add_placement(placement("low", 0.0, 0.0));
+        placer.add(label);
+
+        let mut label = Label::new(20);
+        label.add_placement(placement("high", 5.0, 5.0));
+        placer.add(label);
+
+        let mut label = Label::new(20);
+        label.add_placement(placement("moved", 8.0, 8.0));
+        label.add_placement(placement("moved", 95.0, 30.0));
+        label.add_placement(placement("moved", 30.0, 30.0));
+        placer.add(label);
+
+        let nodes = placer.place();
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].to_string(), "<text>high</text>");
+        assert_eq!(nodes[1].to_string(), "<text>moved</text>");
+        assert_eq!(placer.place().len(), 0);
+    }
+
+    #[test]
+    fn test_obstacle() {
+        let bounds = Rectangle::new(&Point { x: 0.0, y: 0.0 }, &Point { x: 100.0, y: 100.0 });
+        let mut placer = LabelPlacer::new(&bounds);
+        placer.add_obstacle(Rectangle::new(&Point { x: 0.0, y: 0.0 }, &Point { x: 10.0, y: 10.0 }));
+
+        let mut label = Label::new(10);
+        label.add_placement(placement("over", 5.0, 5.0));
+        label.add_placement(placement("beside", 10.0, 0.0));
+        placer.add(label);
+
+        let nodes = placer.place();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].to_string(), "<text>beside</text>");
+    }
+}
@@ -1,7 +1,15 @@
+pub mod datum;
+pub mod font;
+pub mod label;
 pub mod proj;
+pub mod stops;
 pub mod svg;
 pub mod tile;
 
+pub use datum::*;
+pub use font::*;
+pub use label::*;
 pub use proj::*;
+pub use stops::*;
 pub use svg::*;
 pub use tile::*;
\ No newline at end of file
@@ -2,9 +2,13 @@ use std::f64::consts::PI;
 
 use crate::geometry::{Point, Rectangle};
 
-#[derive(Debug, PartialEq)]
+// How longitude/latitude are mapped to the pixels of the tiles at a zoom, 256 pixels per tile
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Proj {
-    WGS84,
+    // Spherical Mercator (EPSG:3857), 1x1 tiles at zoom 0, latitudes beyond ±85.05 are cut off
+    WebMercator,
+    // Plate carrée (EPSG:4326), 2x1 tiles at zoom 0, degrees are spaced evenly
+    Geographic,
 }
 
 #[allow(dead_code)]
@@ -13,26 +17,145 @@ pub mod consts {
     pub const LATITUDE_MAX: f64 = 85.051129;
     pub const LONGITUDE_MIN: f64 = -180.0;
     pub const LONGITUDE_MAX: f64 = 180.0;
+    pub const GEOGRAPHIC_LATITUDE_MIN: f64 = -90.0;
+    pub const GEOGRAPHIC_LATITUDE_MAX: f64 = 90.0;
+    // Deepest zoom served, the tile counts of deeper ones overflow
+    pub const MAX_ZOOM: u64 = 30;
 }
 
 #[allow(dead_code)]
 impl Proj {
     pub fn default() -> Proj {
-        Proj::WGS84
+        Proj::WebMercator
+    }
+
+    pub fn parse(name: &str) -> Option<Proj> {
+        match name.to_lowercase().as_str() {
+            "webmercator" | "3857" | "epsg:3857" => Some(Proj::WebMercator),
+            "geographic" | "4326" | "epsg:4326" => Some(Proj::Geographic),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Proj::WebMercator => "webmercator",
+            Proj::Geographic => "geographic",
+        }
+    }
+
+    // Number of tile columns and rows at a zoom
+    pub fn tile_count(&self, z: u64) -> (u64, u64) {
+        match self {
+            Proj::WebMercator => (1 << z, 1 << z),
+            Proj::Geographic => (2 << z, 1 << z),
+        }
+    }
+
+    // The XYZ row of a TMS row, TMS counts rows from the south
+    pub fn tms_to_xyz(&self, z: u64, y: u64) -> Option<u64> {
+        if z > consts::MAX_ZOOM {
+            return None;
+        }
+        let (_, rows) = self.tile_count(z);
+        (y < rows).then(|| rows - 1 - y)
+    }
+
+    // The z/x/y of a Bing quadkey, each digit picks a quadrant of the tile of the previous ones,
+    // 0 at the top left, 1 top right, 2 bottom left and 3 bottom right
+    pub fn quadkey_to_tile(&self, quadkey: &str) -> Result<(u64, u64, u64), String> {
+        if *self != Proj::WebMercator {
+            return Err(format!("no quadkeys for the {} projection", self.name()));
+        }
+        if quadkey.len() as u64 > consts::MAX_ZOOM {
+            return Err(format!("quadkey '{}' is too long", quadkey));
+        }
+
+        let (mut x, mut y) = (0, 0);
+        for digit in quadkey.chars() {
+            let quadrant = digit.to_digit(4).ok_or_else(|| format!("invalid quadkey '{}'", quadkey))? as u64;
+            x = x * 2 + (quadrant & 1);
+            y = y * 2 + (quadrant >> 1);
+        }
+        Ok((quadkey.len() as u64, x, y))
+    }
+
+    pub fn tile_to_quadkey(&self, z: u64, x: u64, y: u64) -> String {
+        (0..z).rev()
+            .map(|i| char::from(b'0' + ((x >> i) & 1 | ((y >> i) & 1) << 1) as u8))
+            .collect()
+    }
+
+    // Width of the world in pixels, longitudes a world apart are drawn at the same place of the tiles
+    pub fn world_width(&self, z: u64) -> f64 {
+        let width = 256.0 * 2f64.powi(z as i32);
+        match self {
+            Proj::WebMercator => width,
+            Proj::Geographic => width * 2.0,
+        }
+    }
+
+    // Tile columns wrap around the antimeridian, z is at most MAX_ZOOM
+    pub fn wrap_x(&self, z: u64, x: u64) -> u64 {
+        x % self.tile_count(z).0
+    }
+
+    // Latitudes covered by the tiles
+    pub fn latitude_range(&self) -> (f64, f64) {
+        match self {
+            Proj::WebMercator => (consts::LATITUDE_MIN, consts::LATITUDE_MAX),
+            Proj::Geographic => (consts::GEOGRAPHIC_LATITUDE_MIN, consts::GEOGRAPHIC_LATITUDE_MAX),
+        }
     }
 
     pub fn longitude_to_x(&self, v: f64, zoom: f64) -> f64 {
         match self {
-            Proj::WGS84 => 256.0 / (2.0*PI) * (v.to_radians() + PI) * 2.0f64.powf(zoom),
+            Proj::WebMercator => 256.0 / (2.0*PI) * (v.to_radians() + PI) * 2.0f64.powf(zoom),
+            Proj::Geographic => 256.0 / 180.0 * (v + 180.0) * 2.0f64.powf(zoom),
         }
     }
     
     pub fn latitude_to_y(&self,v: f64, zoom: f64) -> f64 {
         match self {
-            Proj::WGS84 => 256.0 / (2.0*PI) * 2.0f64.powf(zoom) * (PI - (PI/4.0 + v.to_radians()/2.0).tan().ln()),
+            Proj::WebMercator => 256.0 / (2.0*PI) * 2.0f64.powf(zoom) * (PI - (PI/4.0 + v.to_radians()/2.0).tan().ln()),
+            Proj::Geographic => 256.0 / 180.0 * (90.0 - v) * 2.0f64.powf(zoom),
+        }
+    }
+
+    pub fn x_to_longitude(&self, x: f64, zoom: f64) -> f64 {
+        match self {
+            Proj::WebMercator => (x * 2.0 * PI / 256.0 / 2.0f64.powf(zoom) - PI).to_degrees(),
+            Proj::Geographic => x * 180.0 / 256.0 / 2.0f64.powf(zoom) - 180.0,
         }
     }
 
+    pub fn y_to_latitude(&self, y: f64, zoom: f64) -> f64 {
+        match self {
+            Proj::WebMercator => (2.0 * (PI - y * 2.0 * PI / 256.0 / 2.0f64.powf(zoom)).exp().atan() - PI / 2.0).to_degrees(),
+            Proj::Geographic => 90.0 - y * 180.0 / 256.0 / 2.0f64.powf(zoom),
+        }
+    }
+
+    // Longitude and latitude of the corners of a tile, in degrees
+    pub fn tile_bounds(&self, z: u64, x: u64, y: u64) -> Rectangle {
+        let rect = self.tile_rect(x, y);
+        let min = Point::new(self.x_to_longitude(rect.min.x, z as f64), self.y_to_latitude(rect.max.y, z as f64));
+        let max = Point::new(self.x_to_longitude(rect.max.x, z as f64), self.y_to_latitude(rect.min.y, z as f64));
+        Rectangle::new(&min, &max)
+    }
+
+    // The tile containing a point, longitudes wrap around the antimeridian and latitudes beyond the
+    // edges of the map are put in the tiles on the edges
+    pub fn lonlat_to_tile(&self, longitude: f64, latitude: f64, z: u64) -> (u64, u64) {
+        let longitude = wrap_longitude(longitude);
+        let (latitude_min, latitude_max) = self.latitude_range();
+        let latitude = latitude.clamp(latitude_min, latitude_max);
+        let (columns, rows) = self.tile_count(z);
+        let x = (self.longitude_to_x(longitude, z as f64) / 256.0).floor().clamp(0.0, (columns - 1) as f64);
+        let y = (self.latitude_to_y(latitude, z as f64) / 256.0).floor().clamp(0.0, (rows - 1) as f64);
+        (x as u64, y as u64)
+    }
+
     pub fn tile_rect(&self, x: u64, y: u64) -> Rectangle {
         let min = Point::new((x * 256) as f64, (y * 256) as f64);
         let max = Point::new(((x+1) * 256) as f64, ((y+1) * 256) as f64);
@@ -46,6 +169,11 @@ impl Proj {
     }
 }
 
+// The same longitude within [-180, 180)
+pub fn wrap_longitude(longitude: f64) -> f64 {
+    (longitude + 180.0).rem_euclid(360.0) - 180.0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -54,7 +182,7 @@ mod tests {
     #[test]
     fn test_to_xy() {
         let proj = Proj::default();
-        assert_eq!(proj, Proj::WGS84);
+        assert_eq!(proj, Proj::WebMercator);
         assert_eq!(proj.longitude_to_x(LONGITUDE_MIN, 0.0), 0.0);
         assert_eq!(proj.longitude_to_x(0.0, 0.0), 128.0);
         assert_eq!(proj.longitude_to_x(LONGITUDE_MAX, 0.0), 256.0);
@@ -63,6 +191,151 @@ mod tests {
         assert!((proj.latitude_to_y(LATITUDE_MIN, 0.0) - 256.0).abs()< 1e-5);
     }
 
+    #[test]
+    fn test_geographic() {
+        let proj = Proj::Geographic;
+        assert_eq!(proj.tile_count(0), (2, 1));
+        assert_eq!(proj.tile_count(3), (16, 8));
+        assert_eq!(proj.longitude_to_x(LONGITUDE_MIN, 0.0), 0.0);
+        assert_eq!(proj.longitude_to_x(0.0, 0.0), 256.0);
+        assert_eq!(proj.longitude_to_x(LONGITUDE_MAX, 0.0), 512.0);
+        assert_eq!(proj.latitude_to_y(GEOGRAPHIC_LATITUDE_MAX, 0.0), 0.0);
+        assert_eq!(proj.latitude_to_y(0.0, 0.0), 128.0);
+        assert_eq!(proj.latitude_to_y(GEOGRAPHIC_LATITUDE_MIN, 0.0), 256.0);
+
+        for zoom in [0.0, 7.0, 16.0] {
+            for (longitude, latitude) in [(-179.5, 89.9), (114.40, 30.67), (-43.2, -22.9)] {
+                let x = proj.longitude_to_x(longitude, zoom);
+                let y = proj.latitude_to_y(latitude, zoom);
+                assert!((proj.x_to_longitude(x, zoom) - longitude).abs() < 1e-9);
+                assert!((proj.y_to_latitude(y, zoom) - latitude).abs() < 1e-9);
+            }
+        }
+
+        let bounds = proj.tile_bounds(0, 1, 0);
+        assert_eq!((bounds.min.x, bounds.min.y), (0.0, GEOGRAPHIC_LATITUDE_MIN));
+        assert_eq!((bounds.max.x, bounds.max.y), (LONGITUDE_MAX, GEOGRAPHIC_LATITUDE_MAX));
+        assert_eq!(proj.lonlat_to_tile(114.40, 30.67, 1), (3, 0));
+        assert_eq!(proj.lonlat_to_tile(179.99, GEOGRAPHIC_LATITUDE_MIN, 2), (7, 3));
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap_longitude(0.0), 0.0);
+        assert_eq!(wrap_longitude(180.0), -180.0);
+        assert_eq!(wrap_longitude(-180.0), -180.0);
+        assert_eq!(wrap_longitude(190.0), -170.0);
+        assert_eq!(wrap_longitude(-190.0), 170.0);
+        assert!((wrap_longitude(474.40) - 114.40).abs() < 1e-9);
+
+        let proj = Proj::default();
+        assert_eq!(proj.wrap_x(3, 9), 1);
+        assert_eq!(proj.wrap_x(3, 7), 7);
+        assert_eq!(Proj::Geographic.wrap_x(0, 3), 1);
+        assert_eq!(proj.world_width(2), 1024.0);
+        assert_eq!(Proj::Geographic.world_width(2), 2048.0);
+        assert_eq!(proj.world_width(MAX_ZOOM), (proj.tile_count(MAX_ZOOM).0 * 256) as f64);
+        assert_eq!(proj.world_width(2), proj.longitude_to_x(LONGITUDE_MAX, 2.0) - proj.longitude_to_x(LONGITUDE_MIN, 2.0));
+    }
+
+    #[test]
+    fn test_tms() {
+        let proj = Proj::default();
+        assert_eq!(proj.tms_to_xyz(0, 0), Some(0));
+        assert_eq!(proj.tms_to_xyz(14, 16383 - 6724), Some(6724));
+        assert_eq!(proj.tms_to_xyz(3, 8), None);
+        assert_eq!(Proj::Geographic.tms_to_xyz(1, 0), Some(1));
+        assert_eq!(proj.tms_to_xyz(64, 0), None);
+    }
+
+    #[test]
+    fn test_quadkey() {
+        let proj = Proj::default();
+        assert_eq!(proj.quadkey_to_tile("213"), Ok((3, 3, 5)));
+        assert_eq!(proj.tile_to_quadkey(3, 3, 5), "213");
+        assert_eq!(proj.tile_to_quadkey(0, 0, 0), "");
+        assert_eq!(proj.quadkey_to_tile(""), Ok((0, 0, 0)));
+
+        let quadkey = proj.tile_to_quadkey(14, 13398, 6724);
+        assert_eq!(quadkey.len(), 14);
+        assert_eq!(proj.quadkey_to_tile(&quadkey), Ok((14, 13398, 6724)));
+
+        assert!(proj.quadkey_to_tile("0124").is_err());
+        assert!(proj.quadkey_to_tile("0x").is_err());
+        assert!(Proj::Geographic.quadkey_to_tile("0").is_err());
+        assert!(proj.quadkey_to_tile(&"3".repeat(MAX_ZOOM as usize)).is_ok());
+        assert!(proj.quadkey_to_tile(&"3".repeat(MAX_ZOOM as usize + 1)).is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Proj::parse("webmercator"), Some(Proj::WebMercator));
+        assert_eq!(Proj::parse("EPSG:3857"), Some(Proj::WebMercator));
+        assert_eq!(Proj::parse("Geographic"), Some(Proj::Geographic));
+        assert_eq!(Proj::parse("4326"), Some(Proj::Geographic));
+        assert_eq!(Proj::parse("wgs84"), None);
+        assert_eq!(Proj::parse(Proj::Geographic.name()), Some(Proj::Geographic));
+    }
+
+    #[test]
+    fn test_to_longitude_latitude() {
+        let proj = Proj::default();
+        assert_eq!(proj.x_to_longitude(0.0, 0.0), LONGITUDE_MIN);
+        assert_eq!(proj.x_to_longitude(128.0, 0.0), 0.0);
+        assert_eq!(proj.y_to_latitude(128.0, 0.0), 0.0);
+        assert!((proj.y_to_latitude(0.0, 0.0) - LATITUDE_MAX).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let proj = Proj::default();
+        for zoom in [0.0, 5.0, 14.0, 20.0] {
+            for longitude in [LONGITUDE_MIN, -73.98, 0.0, 114.40, 179.99] {
+                let x = proj.longitude_to_x(longitude, zoom);
+                assert!((proj.x_to_longitude(x, zoom) - longitude).abs() < 1e-9);
+            }
+            for latitude in [LATITUDE_MIN, -33.86, 0.0, 30.67, 60.0, LATITUDE_MAX] {
+                let y = proj.latitude_to_y(latitude, zoom);
+                assert!((proj.y_to_latitude(y, zoom) - latitude).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tile_bounds() {
+        let proj = Proj::default();
+        let bounds = proj.tile_bounds(0, 0, 0);
+        assert_eq!(bounds.min.x, LONGITUDE_MIN);
+        assert_eq!(bounds.max.x, LONGITUDE_MAX);
+        assert!((bounds.min.y - LATITUDE_MIN).abs() < 1e-5);
+        assert!((bounds.max.y - LATITUDE_MAX).abs() < 1e-5);
+
+        let bounds = proj.tile_bounds(1, 1, 0);
+        assert_eq!((bounds.min.x, bounds.max.x), (0.0, LONGITUDE_MAX));
+        assert_eq!(bounds.min.y, 0.0);
+
+        let bounds = proj.tile_bounds(14, 13398, 6724);
+        assert!(bounds.min.x < 114.40 && 114.40 < bounds.max.x);
+        assert!(bounds.min.y < 30.67 && 30.67 < bounds.max.y);
+    }
+
+    #[test]
+    fn test_lonlat_to_tile() {
+        let proj = Proj::default();
+        assert_eq!(proj.lonlat_to_tile(114.40, 30.67, 14), (13398, 6724));
+        assert_eq!(proj.lonlat_to_tile(0.0, 0.0, 0), (0, 0));
+        assert_eq!(proj.lonlat_to_tile(179.99, LATITUDE_MIN, 3), (7, 7));
+        assert_eq!(proj.lonlat_to_tile(LONGITUDE_MAX, 90.0, 3), (0, 0));
+        assert_eq!(proj.lonlat_to_tile(-200.0, 90.0, 3), (7, 0));
+        assert_eq!(proj.lonlat_to_tile(114.40 + 720.0, 30.67, 14), (13398, 6724));
+
+        for (z, x, y) in [(3, 5, 2), (14, 13398, 6724), (18, 215000, 107000)] {
+            let bounds = proj.tile_bounds(z, x, y);
+            let center = ((bounds.min.x + bounds.max.x) / 2.0, (bounds.min.y + bounds.max.y) / 2.0);
+            assert_eq!(proj.lonlat_to_tile(center.0, center.1, z), (x, y));
+        }
+    }
+
     #[test]
     fn test_tile_rect() {
         let proj = Proj::default();
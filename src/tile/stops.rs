@@ -0,0 +1,69 @@
+// A value that changes with the zoom, interpolated between (zoom, value) stops.
+// The value is clamped to the first and last stop outside of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stops {
+    // Growth rate of the interpolation, 1 is linear, larger values grow faster towards the higher stop
+    pub base: f64,
+    pub stops: Vec<(f64, f64)>,
+}
+
+#[allow(dead_code)]
+impl Stops {
+    // The same value at every zoom
+    pub fn new(value: f64) -> Stops {
+        Stops { base: 1.0, stops: vec![(0.0, value)] }
+    }
+
+    pub fn linear(stops: Vec<(f64, f64)>) -> Stops {
+        Stops::exponential(1.0, stops)
+    }
+
+    pub fn exponential(base: f64, mut stops: Vec<(f64, f64)>) -> Stops {
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Stops { base, stops }
+    }
+
+    pub fn at(&self, z: f64) -> f64 {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return 0.0,
+        };
+        if z <= first.0 {
+            return first.1;
+        }
+        if z >= last.0 {
+            return last.1;
+        }
+
+        let i = self.stops.iter().position(|stop| stop.0 > z).unwrap();
+        let (z0, value0) = self.stops[i - 1];
+        let (z1, value1) = self.stops[i];
+        let t = if self.base == 1.0 {
+            (z - z0) / (z1 - z0)
+        } else {
+            (self.base.powf(z - z0) - 1.0) / (self.base.powf(z1 - z0) - 1.0)
+        };
+        value0 + (value1 - value0) * t
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stops() {
+        assert_eq!(Stops::new(3.0).at(0.0), 3.0);
+        assert_eq!(Stops::new(3.0).at(18.0), 3.0);
+
+        let stops = Stops::linear(vec![(16.0, 8.0), (10.0, 2.0)]);
+        assert_eq!(stops.at(5.0), 2.0);
+        assert_eq!(stops.at(10.0), 2.0);
+        assert_eq!(stops.at(13.0), 5.0);
+        assert_eq!(stops.at(20.0), 8.0);
+
+        let stops = Stops::exponential(2.0, vec![(10.0, 0.0), (12.0, 3.0)]);
+        assert_eq!(stops.at(11.0), 1.0);
+        assert_eq!(stops.at(12.0), 3.0);
+    }
+}
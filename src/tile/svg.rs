@@ -1,13 +1,47 @@
-use resvg::{tiny_skia::Pixmap, usvg::{fontdb::Database as FontDatabase, Tree, Options, Transform}};
+use std::{collections::HashMap, f64::consts::PI, path::Path, sync::Arc};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use resvg::{tiny_skia::{IntRect, Pixmap}, usvg::{fontdb::Database as FontDatabase, Tree, Options, Transform}};
 use crate::{geometry::{Point, Polygon, Polyline, Rectangle}, xml::XmlNode};
-use super::{Proj, Tile};
+use super::{proj::consts::{LONGITUDE_MAX, LONGITUDE_MIN}, Datum, FontMetrics, Label, LabelPlacer, Placement, Proj, Stops, Tile};
 
+const CLIP_BUFFER: f64 = 16.0;
+// Geometries are simplified in pixel space, so the same tolerance drops more detail at lower zooms
+const SIMPLIFY_TOLERANCE: f64 = 0.5;
+// Minimum gap between two labels
+const LABEL_PADDING: f64 = 2.0;
+// Largest change of direction between neighbouring glyphs of a label along a line, in degrees
+const MAX_LABEL_ANGLE: f64 = 30.0;
+// Number of places along a line tried for its label
+const MAX_LABEL_PLACEMENTS: usize = 5;
+// The background is below everything else
+const BACKGROUND_PRIORITY: i32 = i32::MIN;
+// The debug overlay is above everything else
+const DEBUG_PRIORITY: i32 = i32::MAX;
+const DEBUG_COLOR: &str = "#ff0000";
+const GRATICULE_COLOR: &str = "#0000ff";
+// Labels are haloed in this colour unless their props set another
+pub const DEFAULT_HALO_COLOR: &str = "#ffffff";
+// Graticule intervals in degrees, the smallest one keeping lines at least GRATICULE_SPACING pixels apart is used
+const GRATICULE_INTERVALS: [f64; 17] = [0.001, 0.002, 0.005, 0.01, 0.02, 0.05, 0.1, 0.2, 0.5, 1.0, 2.0, 5.0, 10.0, 15.0, 30.0, 45.0, 90.0];
+const GRATICULE_SPACING: f64 = 128.0;
 
 #[allow(dead_code)]
 pub struct PolylineProps {
     pub color: String,
-    pub width: usize,
+    pub width: Stops,
     pub priority: i32,
+    // Lengths of alternating dashes and gaps in pixels, empty draws a solid line
+    pub dash: Vec<f64>,
+    pub opacity: Stops,
+    // butt, round or square
+    pub cap: String,
+    // miter, round or bevel
+    pub join: String,
+    // The casing is a wider stroke drawn under the line, 0 width disables it.
+    // It defaults to just under the line, give it a lower priority than every line so that casings do not cover crossing lines.
+    pub casing_color: String,
+    pub casing_width: Stops,
+    pub casing_priority: i32,
 }
 
 
@@ -16,8 +50,15 @@ impl PolylineProps {
     pub fn new(color: &str, width: usize, priority: i32) -> PolylineProps {
         PolylineProps {
             color: String::from(color),
-            width,
+            width: Stops::new(width as f64),
             priority,
+            dash: Vec::new(),
+            opacity: Stops::new(1.0),
+            cap: String::from("butt"),
+            join: String::from("miter"),
+            casing_color: String::from("#000000"),
+            casing_width: Stops::new(0.0),
+            casing_priority: priority - 1,
         }
     }
 }
@@ -26,9 +67,15 @@ impl PolylineProps {
 #[allow(dead_code)]
 pub struct TextProps {
     pub fill_color: String,
-    pub size: usize,
+    pub size: Stops,
     pub weight: usize,
     pub priority: i32,
+    // The halo is a stroke painted under the fill, 0 width disables it
+    pub halo_color: String,
+    pub halo_width: Stops,
+    pub opacity: Stops,
+    // Distance between repeated labels along a line in pixels, 0 places a single label
+    pub repeat_distance: Stops,
 }
 
 #[allow(dead_code)]
@@ -36,9 +83,13 @@ impl TextProps {
     pub fn new(fill_color: &str, size: usize, weight: usize, priority: i32) -> TextProps {
         TextProps {
             fill_color: String::from(fill_color),
-            size,
+            size: Stops::new(size as f64),
             weight,
             priority,
+            halo_color: String::from(DEFAULT_HALO_COLOR),
+            halo_width: Stops::new(0.0),
+            opacity: Stops::new(1.0),
+            repeat_distance: Stops::new(0.0),
         }
     }
 }
@@ -47,9 +98,15 @@ impl TextProps {
 pub struct PolygonProps {
     pub fill_color: String,
     pub border_color: String,
-    pub border_width: usize,
+    pub border_width: Stops,
     pub fill_priority: i32,
     pub border_priority: i32,
+    pub fill_opacity: Stops,
+    pub border_opacity: Stops,
+    // Lengths of alternating dashes and gaps of the border in pixels, empty draws a solid border
+    pub border_dash: Vec<f64>,
+    // Drawn over the fill color
+    pub pattern: Option<Pattern>,
 }
 
 #[allow(dead_code)]
@@ -58,9 +115,103 @@ impl PolygonProps {
         PolygonProps {
             fill_color: String::from(fill_color),
             border_color: String::from(border_color),
-            border_width,
+            border_width: Stops::new(border_width as f64),
             fill_priority,
             border_priority,
+            fill_opacity: Stops::new(1.0),
+            border_opacity: Stops::new(1.0),
+            border_dash: Vec::new(),
+            pattern: None,
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternKind {
+    Hatch,
+    Cross,
+    Dots,
+}
+
+// A fill repeated every `spacing` pixels, made of lines `width` pixels wide or dots `width` pixels across
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub color: String,
+    pub spacing: f64,
+    pub width: f64,
+    // Clockwise, in degrees
+    pub angle: f64,
+}
+
+#[allow(dead_code)]
+impl Pattern {
+    pub fn new(kind: PatternKind, color: &str, spacing: f64, width: f64) -> Pattern {
+        Pattern { kind, color: String::from(color), spacing, width, angle: 45.0 }
+    }
+}
+
+#[allow(dead_code)]
+pub enum Marker {
+    Circle,
+    Square,
+    // An image embedded as a data URL
+    Icon(String),
+}
+
+#[allow(dead_code)]
+impl Marker {
+    // Loads an SVG, PNG or JPEG icon from disk
+    pub fn icon(file: &str) -> std::io::Result<Marker> {
+        let data = std::fs::read(file)?;
+        let mime = match Path::new(file).extension().and_then(|ext| ext.to_str()) {
+            Some("svg") => "image/svg+xml",
+            Some("png") => "image/png",
+            _ => "image/jpeg",
+        };
+        Ok(Marker::Icon(format!("data:{};base64,{}", mime, BASE64.encode(data))))
+    }
+}
+
+#[allow(dead_code)]
+pub struct BackgroundProps {
+    pub color: String,
+    // Drawn over the color
+    pub pattern: Option<Pattern>,
+}
+
+#[allow(dead_code)]
+impl BackgroundProps {
+    pub fn new(color: &str) -> BackgroundProps {
+        BackgroundProps { color: String::from(color), pattern: None }
+    }
+}
+
+#[allow(dead_code)]
+pub struct PointProps {
+    pub marker: Marker,
+    pub size: Stops,
+    // Clockwise, in degrees
+    pub rotation: Stops,
+    pub fill_color: String,
+    pub border_color: String,
+    pub border_width: Stops,
+    pub priority: i32,
+}
+
+#[allow(dead_code)]
+impl PointProps {
+    pub fn new(marker: Marker, size: usize, fill_color: &str, border_color: &str, border_width: usize, priority: i32) -> PointProps {
+        PointProps {
+            marker,
+            size: Stops::new(size as f64),
+            rotation: Stops::new(0.0),
+            fill_color: String::from(fill_color),
+            border_color: String::from(border_color),
+            border_width: Stops::new(border_width as f64),
+            priority,
         }
     }
 }
@@ -70,137 +221,629 @@ pub struct SvgTile {
     x: u64,
     y: u64,
     z: u64,
+    size: u64,
     proj: Proj,
+    // Datum the WGS-84 data is shifted to before it is projected
+    datum: Datum,
+    // Datum of the features being drawn
+    source_datum: Datum,
+    // Degrees added to the longitudes, a multiple of 360 to draw a copy of the world on a side
+    world_offset: f64,
+    metrics: Arc<FontMetrics>,
     xml: XmlNode,
+    labels: LabelPlacer,
     id_count: usize,
+    // Ids of the patterns already defined, by pattern and background
+    patterns: HashMap<String, String>,
 }
 
 #[allow(dead_code)]
 impl SvgTile {
-    pub fn new(x: u64, y: u64, z: u64, proj: Proj) -> SvgTile {
+    pub fn new(x: u64, y: u64, z: u64, proj: Proj, metrics: Arc<FontMetrics>) -> SvgTile {
+        SvgTile::metatile(x, y, z, 1, proj, metrics)
+    }
+
+    // A canvas of size x size tiles with (x, y) at the top left, labels are only placed where
+    // they fit in the canvas so that they are never cut off between the tiles sliced from it
+    pub fn metatile(x: u64, y: u64, z: u64, size: u64, proj: Proj, metrics: Arc<FontMetrics>) -> SvgTile {
+        let pixels = (size * 256).to_string();
         let mut xml = XmlNode::new("svg", "");
         xml.set_attr("xmlns", "http://www.w3.org/2000/svg");
-        xml.set_attr("width", "256");
-        xml.set_attr("height", "256");
-        xml.set_attr("viewBox", "0, 0, 256, 256");
-        SvgTile { x, y, z, proj, xml, id_count:0 }
+        xml.set_attr("width", &pixels);
+        xml.set_attr("height", &pixels);
+        xml.set_attr("viewBox", &format!("0, 0, {}, {}", pixels, pixels));
+        let canvas = Rectangle::new(&Point::new(0.0, 0.0), &Point::new((size * 256) as f64, (size * 256) as f64));
+        SvgTile { x, y, z, size, proj, datum: Datum::default(), source_datum: Datum::default(), world_offset: 0.0, metrics, xml, labels: LabelPlacer::new(&canvas), id_count:0, patterns: HashMap::new() }
     }
 
-    pub fn text_size(text: &str, text_props: &TextProps) -> Rectangle {
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn datum(&self) -> Datum {
+        self.datum
+    }
+
+    pub fn set_datum(&mut self, datum: Datum) {
+        self.datum = datum;
+    }
+
+    pub fn set_source_datum(&mut self, datum: Datum) {
+        self.source_datum = datum;
+    }
+
+    // A longitude/latitude of the features in the datum of the tile
+    pub fn shift(&self, longitude_latitude: (f64, f64)) -> (f64, f64) {
+        self.source_datum.convert(self.datum, longitude_latitude)
+    }
+
+    pub fn set_world_offset(&mut self, world_offset: f64) {
+        self.world_offset = world_offset;
+    }
+
+    pub fn text_size(&self, text: &str, text_props: &TextProps) -> Rectangle {
         Rectangle { 
             min: Point { 
                 x: 0.0,
                 y: 0.0 
             }, 
             max: Point { 
-                x: (text.len() * text_props.size / 2) as f64, 
-                y: text_props.size as f64
+                x: self.metrics.text_width(text, self.at(&text_props.size), text_props.weight), 
+                y: self.at(&text_props.size)
             }
         }
     }
 
+    // The value of a style property at the zoom of the tile
+    pub fn at(&self, stops: &Stops) -> f64 {
+        stops.at(self.z as f64)
+    }
+
     pub fn sort_tags(&mut self) {
         self.xml.sort();
     }
 
+    pub fn place_labels(&mut self) {
+        for node in self.labels.place() {
+            self.xml.add_node(node);
+        }
+    }
+
+    // The label is centered on the point, or moved above or below it when that space is taken
     pub fn append_text(&mut self, point: &Point, text: &str, props: &TextProps) {
+        let (x, y) = self.local((point.x, point.y));
+        let text_size = self.text_size(text, props);
+        let x = x - text_size.width()/2.0;
+
+        let step = text_size.height() + 2.0 * LABEL_PADDING;
+        let mut label = Label::new(props.priority);
+        for dy in [0.0, -step, step] {
+            let mut node = XmlNode::new("text", text);
+            node.set_attr("x", &x.to_string());
+            node.set_attr("y", &(y + dy).to_string());
+            self.set_text_style(&mut node, props);
+
+            let rect = Rectangle::new(
+                &Point::new(x, y + dy - text_size.height()),
+                &Point::new(x + text_size.width(), y + dy),
+            );
+            label.add_placement(Placement::new(vec![rect.buffer(LABEL_PADDING + self.at(&props.halo_width))], node));
+        }
+        self.labels.add(label);
+    }
+
+    // Paints the whole canvas, so that places without data are not left transparent
+    pub fn append_background(&mut self, props: &BackgroundProps) {
+        let fill = match &props.pattern {
+            Some(pattern) => format!("url(#{})", self.pattern_id(pattern, &props.color)),
+            None => props.color.clone(),
+        };
+        let pixels = (self.size * 256).to_string();
+        let mut node = XmlNode::new("rect", "");
+        node.set_attr("width", &pixels);
+        node.set_attr("height", &pixels);
+        node.set_attr("fill", &fill);
+        node.set_priority(BACKGROUND_PRIORITY);
+        self.xml.add_node(node);
+    }
+
+    // Draws the border and z/x/y of every tile of the canvas, and a longitude/latitude graticule
+    pub fn append_debug(&mut self) {
+        let pixels = (self.size * 256) as f64;
+        let rect = self.rect();
+        let z = self.z as f64;
+
+        // Longitudes are spread evenly along x, a pixel spans the same degrees over the whole canvas
+        let world = self.proj.longitude_to_x(LONGITUDE_MAX, z) - self.proj.longitude_to_x(LONGITUDE_MIN, z);
+        let degrees_per_pixel = (LONGITUDE_MAX - LONGITUDE_MIN) / world;
+        let interval = GRATICULE_INTERVALS.iter()
+            .copied()
+            .find(|interval| *interval >= degrees_per_pixel * GRATICULE_SPACING)
+            .unwrap_or(GRATICULE_INTERVALS[GRATICULE_INTERVALS.len() - 1]);
+        let digits = (-interval.log10().floor()).max(0.0) as usize;
+
+        let proj = &self.proj;
+        let longitudes = graticule_lines(LONGITUDE_MIN, LONGITUDE_MAX, interval, pixels, |longitude| proj.longitude_to_x(longitude, z) - rect.min.x);
+        let (latitude_min, latitude_max) = proj.latitude_range();
+        let latitudes = graticule_lines(latitude_min, latitude_max, interval, pixels, |latitude| proj.latitude_to_y(latitude, z) - rect.min.y);
+        for (longitude, x) in longitudes {
+            self.append_debug_line((x, 0.0), (x, pixels), GRATICULE_COLOR);
+            self.append_debug_text((x + 2.0, pixels - 4.0), &format!("{:.*}°", digits, longitude), GRATICULE_COLOR);
+        }
+        for (latitude, y) in latitudes {
+            self.append_debug_line((0.0, y), (pixels, y), GRATICULE_COLOR);
+            self.append_debug_text((pixels - 64.0, y - 4.0), &format!("{:.*}°", digits, latitude), GRATICULE_COLOR);
+        }
+
+        for row in 0..self.size {
+            for column in 0..self.size {
+                let (left, top) = ((column * 256) as f64, (row * 256) as f64);
+                let mut node = XmlNode::new("rect", "");
+                node.set_attr("x", &left.to_string());
+                node.set_attr("y", &top.to_string());
+                node.set_attr("width", "256");
+                node.set_attr("height", "256");
+                node.set_attr("style", &format!("fill:none;stroke:{};stroke-width:1", DEBUG_COLOR));
+                node.set_priority(DEBUG_PRIORITY);
+                self.xml.add_node(node);
+
+                let text = format!("{}/{}/{}", self.z, self.x + column, self.y + row);
+                self.append_debug_text((left + 4.0, top + 16.0), &text, DEBUG_COLOR);
+            }
+        }
+    }
+
+    fn append_debug_line(&mut self, from: (f64, f64), to: (f64, f64), color: &str) {
+        let mut node = XmlNode::new("line", "");
+        node.set_attr("x1", &from.0.to_string());
+        node.set_attr("y1", &from.1.to_string());
+        node.set_attr("x2", &to.0.to_string());
+        node.set_attr("y2", &to.1.to_string());
+        node.set_attr("style", &format!("stroke:{};stroke-width:1;stroke-dasharray:4,4", color));
+        node.set_priority(DEBUG_PRIORITY);
+        self.xml.add_node(node);
+    }
+
+    fn append_debug_text(&mut self, position: (f64, f64), text: &str, color: &str) {
         let mut node = XmlNode::new("text", text);
+        node.set_attr("x", &position.0.to_string());
+        node.set_attr("y", &position.1.to_string());
+        self.set_text_style(&mut node, &TextProps {
+            halo_width: Stops::new(2.0),
+            ..TextProps::new(color, 12, 400, DEBUG_PRIORITY)
+        });
+        self.xml.add_node(node);
+    }
+
+    pub fn append_point(&mut self, point: &Point, props: &PointProps) {
         let (x, y) = self.local((point.x, point.y));
-        let text_size = SvgTile::text_size(text, props);
-        node.set_attr("x", &(x - text_size.width()/2.0).to_string());
-        node.set_attr("y", &y.to_string());
-        node.set_attr("fill", &props.fill_color);
-        node.set_attr("font-size", &props.size.to_string());
-        node.set_attr("font-weight", &props.weight.to_string());
+        if !self.clip_rect().contains(&Point::new(x, y)) {
+            return;
+        }
+
+        let size = self.at(&props.size);
+        let mut node = match &props.marker {
+            Marker::Circle => {
+                let mut node = XmlNode::new("circle", "");
+                node.set_attr("cx", &x.to_string());
+                node.set_attr("cy", &y.to_string());
+                node.set_attr("r", &(size / 2.0).to_string());
+                node
+            },
+            Marker::Square => {
+                let mut node = XmlNode::new("rect", "");
+                node.set_attr("x", &(x - size / 2.0).to_string());
+                node.set_attr("y", &(y - size / 2.0).to_string());
+                node.set_attr("width", &size.to_string());
+                node.set_attr("height", &size.to_string());
+                node
+            },
+            Marker::Icon(href) => {
+                let mut node = XmlNode::new("image", "");
+                node.set_attr("href", href);
+                node.set_attr("x", &(x - size / 2.0).to_string());
+                node.set_attr("y", &(y - size / 2.0).to_string());
+                node.set_attr("width", &size.to_string());
+                node.set_attr("height", &size.to_string());
+                node
+            },
+        };
+
+        if !matches!(props.marker, Marker::Icon(_)) {
+            let border_width = self.at(&props.border_width);
+            node.set_attr("style", &format!("fill:{};stroke:{};stroke-width:{}", &props.fill_color, &props.border_color, border_width));
+        }
+        let rotation = self.at(&props.rotation);
+        if rotation != 0.0 {
+            node.set_attr("transform", &format!("rotate({} {} {})", rotation, x, y));
+        }
         node.set_priority(props.priority);
         self.xml.add_node(node);
+
+        let half = self.marker_half(props);
+        self.labels.add_obstacle(Rectangle::new(&Point::new(x - half, y - half), &Point::new(x + half, y + half)));
     }
 
+    // Half the size of a symbol with its border
+    fn marker_half(&self, props: &PointProps) -> f64 {
+        let border_width = match props.marker {
+            Marker::Icon(_) => 0.0,
+            _ => self.at(&props.border_width),
+        };
+        (self.at(&props.size) + border_width) / 2.0
+    }
+
+    // The label is put to the right of the symbol, or to the left, above or below it when that space is taken
+    pub fn append_point_text(&mut self, point: &Point, text: &str, point_props: &PointProps, text_props: &TextProps) {
+        let (x, y) = self.local((point.x, point.y));
+        let text_size = self.text_size(text, text_props);
+        let (width, height) = (text_size.width(), text_size.height());
+        let gap = self.marker_half(point_props) + LABEL_PADDING + self.at(&text_props.halo_width);
+
+        let mut label = Label::new(text_props.priority);
+        for (left, baseline) in [
+            (x + gap, y + height / 3.0),
+            (x - gap - width, y + height / 3.0),
+            (x - width / 2.0, y - gap),
+            (x - width / 2.0, y + gap + height),
+        ] {
+            let mut node = XmlNode::new("text", text);
+            node.set_attr("x", &left.to_string());
+            node.set_attr("y", &baseline.to_string());
+            self.set_text_style(&mut node, text_props);
+
+            let rect = Rectangle::new(
+                &Point::new(left, baseline - height),
+                &Point::new(left + width, baseline),
+            );
+            label.add_placement(Placement::new(vec![rect.buffer(LABEL_PADDING + self.at(&text_props.halo_width))], node));
+        }
+        self.labels.add(label);
+    }
+
+    // The label follows the straightest part of the line that is long enough for the text,
+    // other good parts are tried when that space is taken.
+    // With a repeat distance the line is cut into sections of that length, each with its own label.
     pub fn append_text_path(&mut self, polyline: &Polyline, text: &str, line_props: &PolylineProps, text_props: &TextProps) {
-        let mut data = String::new();
-        let mut first = true;
-        let mut prev = Point::uninitialized();
-        let mut path_length = 0.0;
-        for point in polyline.points() {
-            let (x, y) = self.local((point.x, point.y));
-            if first {
-                data.push_str(&format!("M{},{}", x, y));
-                first = false;
-                prev.x = x;
-                prev.y = y;
-            } else {
-                data.push_str(&format!(" L{},{}", x, y));
-                let current = Point::new(x, y);
-                path_length += prev.distance(&current);
-                prev = current;
+        self.append_polyline(polyline, line_props);
+
+        let text_width = self.text_size(text, text_props).width();
+        let pieces = self.clipped_polyline(polyline);
+        let repeat_distance = self.at(&text_props.repeat_distance);
+        let mut sections = Vec::new();
+        if repeat_distance > 0.0 {
+            for piece in &pieces {
+                let length = piece.length();
+                let mut from = 0.0;
+                while from < length {
+                    let to = (from + repeat_distance).min(length);
+                    sections.push(vec![(piece, from, to)]);
+                    from = to;
+                }
             }
+        } else {
+            sections.push(pieces.iter().map(|piece| (piece, 0.0, piece.length())).collect());
         }
 
-        self.id_count = self.id_count + 1;
-        let id: String = format!("ID_{}", self.id_count);
-        let mut path = XmlNode::new("path", "");
-        path.set_attr("id", &id);
-        path.set_attr("fill", "none");
-        path.set_attr("stroke", &line_props.color);
-        path.set_attr("stroke-width", &line_props.width.to_string());
-        path.set_attr("d", &data);
-        path.set_priority(line_props.priority);
-        self.xml.add_node(path);
+        for section in sections {
+            self.append_path_label(&section, text, text_width, text_props);
+        }
+    }
 
-        let offset = (path_length - SvgTile::text_size(text, text_props).width()) / 2.0;
+    fn append_path_label(&mut self, section: &[(&Polyline, f64, f64)], text: &str, text_width: f64, text_props: &TextProps) {
+        let font_size = self.at(&text_props.size);
+        let size = font_size + 2.0 * self.at(&text_props.halo_width);
+        let mut label = Label::new(text_props.priority);
+        for path in SvgTile::label_paths(section, text_width, font_size) {
+            self.id_count += 1;
+            let id: String = format!("ID_{}", self.id_count);
+            let mut path_node = XmlNode::new("path", "");
+            path_node.set_attr("id", &id);
+            path_node.set_attr("d", &SvgTile::path_data(path.points()));
+            let mut defs = XmlNode::new("defs", "");
+            defs.add_node(path_node);
 
-        if offset > 10.0 {
-            let mut text_path = XmlNode::new("textPath", &text);
+            let mut text_path = XmlNode::new("textPath", text);
             text_path.set_attr("href", &format!("#{}", &id));
-            text_path.set_attr("startOffset", &offset.to_string());
-            text_path.set_attr("font-size", &text_props.size.to_string());
-            text_path.set_attr("font-weight", &text_props.weight.to_string());
-            text_path.set_attr("fill", &text_props.fill_color);
-            
-            let mut text = XmlNode::new("text", "");
-            text.add_node(text_path);
-            text.set_priority(text_props.priority);
-            self.xml.add_node(text);
+            let mut text_node = XmlNode::new("text", "");
+            text_node.add_node(text_path);
+            self.set_text_style(&mut text_node, text_props);
+
+            let mut node = XmlNode::new("g", "");
+            node.add_node(defs);
+            node.add_node(text_node);
+            node.set_priority(text_props.priority);
+
+            let boxes = SvgTile::path_boxes(&path, 0.0, text_width, size);
+            label.add_placement(Placement::new(boxes, node));
         }
+        self.labels.add(label);
     }
 
     pub fn append_polyline(&mut self, polyline: &Polyline, props: &PolylineProps) {
-        let mut node = XmlNode::new("polyline", "");
-        let mut points = Vec::new();
-        for point in polyline.points() {
-            let (x, y) = self.local((point.x, point.y));
-            points.push(format!("{},{}", x, y));
+        let width = self.at(&props.width);
+        let casing_width = self.at(&props.casing_width);
+        let opacity = self.at(&props.opacity);
+        let mut style = format!(
+            "fill:none;stroke:{};stroke-width:{};stroke-linecap:{};stroke-linejoin:{};stroke-opacity:{}", 
+            &props.color, width, &props.cap, &props.join, opacity
+        );
+        if !props.dash.is_empty() {
+            style += &format!(";stroke-dasharray:{}", SvgTile::dash_array(&props.dash));
         }
-        let points = points.join(" ");
-        node.set_attr("points", &points);
+        let casing_style = format!(
+            "fill:none;stroke:{};stroke-width:{};stroke-linecap:{};stroke-linejoin:{};stroke-opacity:{}", 
+            &props.casing_color, width + casing_width * 2.0, &props.cap, &props.join, opacity
+        );
 
-        let style = format!("fill:none;stroke:{};stroke-width:{}", &props.color, &props.width);
-        node.set_attr("style", &style);
-        node.set_priority(props.priority);
-        self.xml.add_node(node);
+        for piece in self.clipped_polyline(polyline) {
+            let points = SvgTile::points(piece.points());
+            if casing_width > 0.0 {
+                let mut node = XmlNode::new("polyline", "");
+                node.set_attr("points", &points);
+                node.set_attr("style", &casing_style);
+                node.set_priority(props.casing_priority);
+                self.xml.add_node(node);
+            }
+
+            let mut node = XmlNode::new("polyline", "");
+            node.set_attr("points", &points);
+            node.set_attr("style", &style);
+            node.set_priority(props.priority);
+            self.xml.add_node(node);
+        }
     }
 
     pub fn append_polygon(&mut self, polygon: &Polygon, props: &PolygonProps) {
-        let mut points = Vec::new();
-        for point in polygon.points() {
-            let (x, y) = self.local((point.x, point.y));
-            points.push(format!("{},{}", x, y));
+        // Clipped before it is simplified, so that only the visible part of large polygons is simplified
+        let polygon = self.local_polygon(polygon).clip(&self.clip_rect()).simplify(SIMPLIFY_TOLERANCE);
+        if polygon.points_count() < 3 {
+            return;
         }
-        let points = points.join(" ");
+        // The holes are subpaths of the outer ring, cut out by the even-odd fill
+        let rings: Vec<&Vec<Point>> = std::iter::once(polygon.points())
+            .chain(polygon.holes().iter().map(|hole| hole.points()).filter(|points| points.len() >= 3))
+            .collect();
+        let path = rings.iter()
+            .map(|ring| format!("M{}Z", SvgTile::points(ring)))
+            .collect::<Vec<String>>()
+            .join(" ");
 
-        let mut polygon_node = XmlNode::new("polygon", "");
-        let style = format!("fill:{};stroke:{};", &props.fill_color, &props.fill_color);
-        polygon_node.set_attr("points", &points);
+        let fill = match &props.pattern {
+            Some(pattern) => format!("url(#{})", self.pattern_id(pattern, &props.fill_color)),
+            None => props.fill_color.clone(),
+        };
+        let fill_opacity = self.at(&props.fill_opacity);
+        let mut polygon_node = XmlNode::new("path", "");
+        let style = format!(
+            "fill:{};stroke:{};fill-opacity:{};stroke-opacity:{}", 
+            &fill, &props.fill_color, fill_opacity, fill_opacity
+        );
+        polygon_node.set_attr("d", &path);
+        polygon_node.set_attr("fill-rule", "evenodd");
         polygon_node.set_attr("style", &style);
         polygon_node.set_priority(props.fill_priority);
         self.xml.add_node(polygon_node);
 
-        let mut polyline_node = XmlNode::new("polyline", "");
-        let style = format!("fill:none;stroke:{};stroke-width:{}", &props.border_color, &props.border_width);
-        polyline_node.set_attr("points", &points);
-        polyline_node.set_attr("style", &style);
-        polyline_node.set_priority(props.border_priority);
-        self.xml.add_node(polyline_node);
+        let mut style = format!(
+            "fill:none;stroke:{};stroke-width:{};stroke-opacity:{}", 
+            &props.border_color, self.at(&props.border_width), self.at(&props.border_opacity)
+        );
+        if !props.border_dash.is_empty() {
+            style += &format!(";stroke-dasharray:{}", SvgTile::dash_array(&props.border_dash));
+        }
+        for ring in rings {
+            let mut polyline_node = XmlNode::new("polyline", "");
+            polyline_node.set_attr("points", &SvgTile::points(ring));
+            polyline_node.set_attr("style", &style);
+            polyline_node.set_priority(props.border_priority);
+            self.xml.add_node(polyline_node);
+        }
+    }
+
+    // Defines the pattern over the background color once, in a <defs> below everything else
+    fn pattern_id(&mut self, pattern: &Pattern, background: &str) -> String {
+        let key = format!("{:?}{}", pattern, background);
+        if let Some(id) = self.patterns.get(&key) {
+            return id.clone();
+        }
+
+        self.id_count += 1;
+        let id = format!("ID_{}", self.id_count);
+        let spacing = pattern.spacing.to_string();
+        let half = (pattern.spacing / 2.0).to_string();
+        let mut pattern_node = XmlNode::new("pattern", "");
+        pattern_node.set_attr("id", &id);
+        pattern_node.set_attr("width", &spacing);
+        pattern_node.set_attr("height", &spacing);
+        pattern_node.set_attr("patternUnits", "userSpaceOnUse");
+        pattern_node.set_attr("patternTransform", &format!("rotate({})", pattern.angle));
+
+        if background != "none" {
+            let mut rect = XmlNode::new("rect", "");
+            rect.set_attr("width", &spacing);
+            rect.set_attr("height", &spacing);
+            rect.set_attr("fill", background);
+            pattern_node.add_node(rect);
+        }
+
+        let mut lines = Vec::new();
+        match pattern.kind {
+            PatternKind::Hatch => lines.push((half.as_str(), "0", half.as_str(), spacing.as_str())),
+            PatternKind::Cross => {
+                lines.push((half.as_str(), "0", half.as_str(), spacing.as_str()));
+                lines.push(("0", half.as_str(), spacing.as_str(), half.as_str()));
+            },
+            PatternKind::Dots => {
+                let mut circle = XmlNode::new("circle", "");
+                circle.set_attr("cx", &half);
+                circle.set_attr("cy", &half);
+                circle.set_attr("r", &(pattern.width / 2.0).to_string());
+                circle.set_attr("fill", &pattern.color);
+                pattern_node.add_node(circle);
+            },
+        }
+        for (x1, y1, x2, y2) in lines {
+            let mut line = XmlNode::new("line", "");
+            line.set_attr("x1", x1);
+            line.set_attr("y1", y1);
+            line.set_attr("x2", x2);
+            line.set_attr("y2", y2);
+            line.set_attr("stroke", &pattern.color);
+            line.set_attr("stroke-width", &pattern.width.to_string());
+            pattern_node.add_node(line);
+        }
+
+        let mut defs = XmlNode::new("defs", "");
+        defs.add_node(pattern_node);
+        defs.set_priority(BACKGROUND_PRIORITY);
+        self.xml.add_node(defs);
+        self.patterns.insert(key, id.clone());
+        id
+    }
+
+    // Geometries are clipped to the tile plus a buffer, so strokes on the edge are not cut off
+    fn clip_rect(&self) -> Rectangle {
+        let pixels = (self.size * 256) as f64;
+        Rectangle::new(&Point::new(0.0, 0.0), &Point::new(pixels, pixels)).buffer(CLIP_BUFFER)
+    }
+
+    // The pieces of a line on the canvas, clipped before they are simplified so that only the visible part
+    // of long lines is simplified
+    fn clipped_polyline(&self, polyline: &Polyline) -> Vec<Polyline> {
+        self.local_polyline(polyline)
+            .clip(&self.clip_rect())
+            .iter()
+            .map(|piece| piece.simplify(SIMPLIFY_TOLERANCE))
+            .collect()
+    }
+
+    fn local_polyline(&self, polyline: &Polyline) -> Polyline {
+        let mut local = Polyline::new();
+        for point in polyline.points() {
+            let (x, y) = self.local((point.x, point.y));
+            local.append(&Point::new(x, y));
+        }
+        local
+    }
+
+    fn local_polygon(&self, polygon: &Polygon) -> Polygon {
+        let mut local = Polygon::new();
+        for point in polygon.points() {
+            let (x, y) = self.local((point.x, point.y));
+            local.append(&Point::new(x, y));
+        }
+        for hole in polygon.holes() {
+            local.add_hole(self.local_polygon(hole));
+        }
+        local
+    }
+
+    fn set_text_style(&self, node: &mut XmlNode, props: &TextProps) {
+        let halo_width = self.at(&props.halo_width);
+        let opacity = self.at(&props.opacity);
+        node.set_attr("fill", &props.fill_color);
+        node.set_attr("font-size", &self.at(&props.size).to_string());
+        node.set_attr("font-weight", &props.weight.to_string());
+        if halo_width > 0.0 {
+            node.set_attr("stroke", &props.halo_color);
+            node.set_attr("stroke-width", &(halo_width * 2.0).to_string());
+            node.set_attr("stroke-linejoin", "round");
+            node.set_attr("paint-order", "stroke");
+        }
+        if opacity < 1.0 {
+            node.set_attr("opacity", &opacity.to_string());
+        }
+        node.set_priority(props.priority);
+    }
+
+    // Parts of the paths between from and to as long as the text, straightest first. Parts that bend more than
+    // MAX_LABEL_ANGLE between neighbouring glyphs are skipped, the rest are turned to read left to right.
+    fn label_paths(paths: &[(&Polyline, f64, f64)], length: f64, size: f64) -> Vec<Polyline> {
+        let step = size / 2.0;
+        let mut candidates = Vec::new();
+        for &(path, from, to) in paths {
+            let mut start = from;
+            while start + length <= to {
+                let (max_angle, turning) = SvgTile::path_bend(path, start, length, step);
+                if max_angle <= MAX_LABEL_ANGLE.to_radians() {
+                    // Prefer the middle of the range among equally straight parts
+                    let center = ((start + length / 2.0) - (from + to) / 2.0).abs() / (to - from);
+                    candidates.push((turning + center * 0.1, path, start));
+                }
+                start += step;
+            }
+        }
+
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+        candidates.into_iter()
+            .take(MAX_LABEL_PLACEMENTS)
+            .map(|(_, path, start)| {
+                let slice = path.slice(start, start + length);
+                if slice.point(0).x > slice.point(slice.points_count() - 1).x {
+                    slice.reversed()
+                } else {
+                    slice
+                }
+            })
+            .collect()
+    }
+
+    // The largest and the total change of direction along a part of the path, sampled every step
+    fn path_bend(path: &Polyline, start: f64, length: f64, step: f64) -> (f64, f64) {
+        let count = (length / step).ceil().max(1.0) as usize;
+        let points: Vec<Point> = (0..=count)
+            .map(|i| path.point_at(start + length * i as f64 / count as f64))
+            .collect();
+        let directions: Vec<f64> = points.windows(2)
+            .map(|pair| (pair[1].y - pair[0].y).atan2(pair[1].x - pair[0].x))
+            .collect();
+
+        let mut max_angle: f64 = 0.0;
+        let mut turning = 0.0;
+        for pair in directions.windows(2) {
+            let mut angle = (pair[1] - pair[0]).abs();
+            if angle > PI {
+                angle = 2.0 * PI - angle;
+            }
+            max_angle = max_angle.max(angle);
+            turning += angle;
+        }
+        (max_angle, turning)
+    }
+
+    // Covers the text laid along the path with squares of the font size
+    fn path_boxes(path: &Polyline, start: f64, length: f64, size: f64) -> Vec<Rectangle> {
+        let count = (length / size).ceil() as usize;
+        (0..count).map(|i| {
+            let center = path.point_at(start + (i as f64 + 0.5) * size);
+            let half = Point::new(size / 2.0, size / 2.0);
+            Rectangle::new(&(&center - &half), &(&center + &half)).buffer(LABEL_PADDING)
+        }).collect()
+    }
+
+    fn dash_array(dash: &[f64]) -> String {
+        let dash: Vec<String> = dash.iter().map(|length| length.to_string()).collect();
+        dash.join(",")
+    }
+
+    fn path_data(points: &[Point]) -> String {
+        let mut data = String::new();
+        for (i, point) in points.iter().enumerate() {
+            if i == 0 {
+                data.push_str(&format!("M{},{}", point.x, point.y));
+            } else {
+                data.push_str(&format!(" L{},{}", point.x, point.y));
+            }
+        }
+        data
+    }
+
+    fn points(points: &[Point]) -> String {
+        points.iter()
+            .map(|point| format!("{},{}", point.x, point.y))
+            .collect::<Vec<String>>()
+            .join(" ")
     }
 }
 
@@ -222,11 +865,14 @@ impl Tile for SvgTile {
     }
 
     fn local(&self, longitude_latitude: (f64, f64)) -> (f64, f64) {
-        self.proj().tile_local(self.x(), self.y(), self.z(), longitude_latitude)
+        let (longitude, latitude) = self.shift(longitude_latitude);
+        self.proj().tile_local(self.x(), self.y(), self.z(), (longitude + self.world_offset, latitude))
     }
 
     fn rect(&self) -> Rectangle {
-        self.proj().tile_rect(self.x(), self.y())
+        let min = self.proj().tile_rect(self.x(), self.y()).min;
+        let max = self.proj().tile_rect(self.x() + self.size - 1, self.y() + self.size - 1).max;
+        Rectangle::new(&min, &max)
     }
 
     fn dump(&self) -> Vec<u8> {
@@ -234,34 +880,88 @@ impl Tile for SvgTile {
     }
 }
 
+// The multiples of interval between min and max degrees drawn within [0, pixels] of the canvas, with their pixel.
+// Projections are monotonic, so the first one is found by bisection instead of walking from min.
+fn graticule_lines(min: f64, max: f64, interval: f64, pixels: f64, pixel: impl Fn(f64) -> f64) -> Vec<(f64, f64)> {
+    let increasing = pixel(max) > pixel(min);
+    let before_canvas = |i: i64| {
+        let p = pixel(i as f64 * interval);
+        if increasing { p < 0.0 } else { p > pixels }
+    };
+
+    let (mut low, mut high) = ((min / interval).ceil() as i64, (max / interval).floor() as i64 + 1);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if before_canvas(middle) {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut i = low;
+    while i as f64 * interval <= max {
+        let degrees = i as f64 * interval;
+        let p = pixel(degrees);
+        if !(0.0..=pixels).contains(&p) {
+            break;
+        }
+        lines.push((degrees, p));
+        i += 1;
+    }
+    lines
+}
+
 pub struct SvgRender {
-    fontdb: FontDatabase
+    fontdb: Arc<FontDatabase>,
+    metrics: Arc<FontMetrics>,
 }
 
+#[allow(dead_code)]
 impl SvgRender {
     pub fn new() -> SvgRender {
         let mut fontdb = FontDatabase::new();
         fontdb.load_system_fonts();
-        SvgRender { fontdb }
+        let fontdb = Arc::new(fontdb);
+        let metrics = Arc::new(FontMetrics::new(fontdb.clone()));
+        SvgRender { fontdb, metrics }
+    }
+
+    pub fn metrics(&self) -> Arc<FontMetrics> {
+        self.metrics.clone()
     }
 
     pub fn to_png(&self, svg: &Vec<u8>) -> Option<Vec<u8>> {
-        let pixmap = Pixmap::new(256, 256);
-        if pixmap.is_none() {
-            None
-        } else {
-            let tree = Tree::from_data(svg, &Options::default(), &self.fontdb);
-            match tree {
-                Ok(tree) => {
-                    let mut pixmap = pixmap.unwrap();
-                    resvg::render(&tree, Transform::from_rotate(0.0), &mut pixmap.as_mut());
-                    Some(pixmap.encode_png().unwrap())
-                }
-                Err(err) => {
-                    println!("{}", String::from_utf8((*svg).clone()).unwrap());
-                    println!("{}", err);
-                    None
-                }
+        let pixmap = self.render(svg, 256)?;
+        Some(pixmap.encode_png().unwrap())
+    }
+
+    // Renders a metatile and slices it into size x size tiles, row by row
+    pub fn to_pngs(&self, svg: &Vec<u8>, size: u64) -> Option<Vec<Vec<u8>>> {
+        let pixmap = self.render(svg, (size * 256) as u32)?;
+        let mut pngs = Vec::new();
+        for y in 0..size {
+            for x in 0..size {
+                let rect = IntRect::from_xywh((x * 256) as i32, (y * 256) as i32, 256, 256)?;
+                pngs.push(pixmap.clone_rect(rect)?.encode_png().unwrap());
+            }
+        }
+        Some(pngs)
+    }
+
+    fn render(&self, svg: &Vec<u8>, pixels: u32) -> Option<Pixmap> {
+        let mut pixmap = Pixmap::new(pixels, pixels)?;
+        let tree = Tree::from_data(svg, &Options::default(), &self.fontdb);
+        match tree {
+            Ok(tree) => {
+                resvg::render(&tree, Transform::from_rotate(0.0), &mut pixmap.as_mut());
+                Some(pixmap)
+            }
+            Err(err) => {
+                println!("{}", String::from_utf8((*svg).clone()).unwrap());
+                println!("{}", err);
+                None
             }
         }
     }
@@ -275,11 +975,11 @@ mod tests {
     #[test]
     fn test_svg_tile() {
         let proj = Proj::default();
-        let svg = SvgTile::new(13398, 6724, 14, proj);
+        let svg = SvgTile::new(13398, 6724, 14, proj, SvgRender::new().metrics());
         assert_eq!(svg.x(), 13398);
         assert_eq!(svg.y(), 6724);
         assert_eq!(svg.z(), 14);
-        assert_eq!(*svg.proj(), Proj::WGS84);
+        assert_eq!(*svg.proj(), Proj::WebMercator);
 
         let rect = svg.rect();
         assert_eq!(rect.min, Point{x: 13398.0 * 256.0, y: 6724.0 * 256.0});
@@ -292,7 +992,7 @@ mod tests {
     #[test]
     fn test_svg_draw() {
         let proj = Proj::default();
-        let mut svg = SvgTile::new(0, 0, 0, proj);
+        let mut svg = SvgTile::new(0, 0, 0, proj, SvgRender::new().metrics());
         svg.append_text(
             &Point { x: -180.0, y: 0.0 }, 
             "hello world", 
@@ -325,10 +1025,394 @@ mod tests {
             &PolygonProps::new("green", "cyan", 3, 5, 10)
         );
 
+        svg.place_labels();
         svg.sort_tags();
         println!("SVG:{}", String::from_utf8(svg.dump()).unwrap());
     }
 
+    #[test]
+    fn test_svg_metatile() {
+        let proj = Proj::default();
+        let svg = SvgTile::metatile(13396, 6724, 14, 4, proj, SvgRender::new().metrics());
+        assert_eq!(svg.size(), 4);
+
+        let rect = svg.rect();
+        assert_eq!(rect.min, Point{x: 13396.0 * 256.0, y: 6724.0 * 256.0});
+        assert_eq!(rect.max, Point{x: 13400.0 * 256.0, y: 6728.0 * 256.0});
+
+        let pos = svg.local((114.40,30.67));
+        assert_eq!((120.6044444446452 + 512.0, 77.42093603871763), pos);
+
+        // A world to the east, the point is drawn at the same place
+        let mut svg = SvgTile::metatile(13396, 6724, 14, 4, proj, SvgRender::new().metrics());
+        svg.set_world_offset(-360.0);
+        assert_eq!(svg.local((114.40 + 360.0, 30.67)), pos);
+
+        // Shifted by a few hundred metres towards the south east on Amap
+        let mut svg = SvgTile::metatile(13396, 6724, 14, 4, proj, SvgRender::new().metrics());
+        svg.set_datum(Datum::GCJ02);
+        let shifted = svg.local((114.40,30.67));
+        assert!(shifted.0 > pos.0 + 10.0 && shifted.1 > pos.1 + 5.0);
+
+        // Features already in the datum of the tile are not shifted
+        let mut gcj = SvgTile::metatile(13396, 6724, 14, 4, proj, SvgRender::new().metrics());
+        gcj.set_datum(Datum::GCJ02);
+        gcj.set_source_datum(Datum::GCJ02);
+        assert_eq!(gcj.local((114.40, 30.67)), pos);
+
+        let render = SvgRender::new();
+        let pngs = render.to_pngs(&svg.dump(), 4).unwrap();
+        assert_eq!(pngs.len(), 16);
+    }
+
+    #[test]
+    fn test_svg_halo() {
+        let proj = Proj::default();
+        let mut svg = SvgTile::new(0, 0, 0, proj, SvgRender::new().metrics());
+        svg.append_text(
+            &Point { x: 0.0, y: 0.0 }, 
+            "hello world", 
+            &TextProps { halo_color: String::from("white"), halo_width: Stops::new(2.0), opacity: Stops::new(0.5), ..TextProps::new("red", 32, 700, 10) }
+        );
+        svg.place_labels();
+
+        let data = String::from_utf8(svg.dump()).unwrap();
+        assert!(data.contains(r#"stroke="white""#));
+        assert!(data.contains(r#"stroke-width="4""#));
+        assert!(data.contains(r#"paint-order="stroke""#));
+        assert!(data.contains(r#"opacity="0.5""#));
+    }
+
+    #[test]
+    fn test_svg_text_path() {
+        let proj = Proj::default();
+        let mut svg = SvgTile::new(0, 0, 0, proj, SvgRender::new().metrics());
+
+        // Drawn east to west with a sharp corner near the east end
+        let mut polyline = Polyline::new();
+        polyline.append(&Point { x: 30.0, y: -60.0 });
+        polyline.append(&Point { x: 30.0, y: 0.0 });
+        polyline.append(&Point { x: -150.0, y: 0.0 });
+        svg.append_text_path(
+            &polyline, 
+            "TEXT PATH", 
+            &PolylineProps::new("green", 3, 10), 
+            &TextProps::new("blue", 16, 700, 10)
+        );
+        svg.place_labels();
+
+        let data = String::from_utf8(svg.dump()).unwrap();
+        let start = data.find(r#" d="M"#).unwrap() + 5;
+        let end = start + data[start..].find('"').unwrap();
+        let points: Vec<(f64, f64)> = data[start..end]
+            .split([' ', 'L'])
+            .filter(|point| !point.is_empty())
+            .map(|point| {
+                let (x, y) = point.split_once(',').unwrap();
+                (x.parse().unwrap(), y.parse().unwrap())
+            })
+            .collect();
+
+        assert_eq!(points.len(), 2);
+        assert!(points[0].0 < points[1].0);
+        assert_eq!(points[0].1, 128.0);
+        assert_eq!(points[1].1, 128.0);
+    }
+
+    #[test]
+    fn test_svg_repeat_labels() {
+        let proj = Proj::default();
+        let mut polyline = Polyline::new();
+        polyline.append(&Point { x: -170.0, y: 0.0 });
+        polyline.append(&Point { x: 170.0, y: 0.0 });
+
+        let mut svg = SvgTile::new(0, 0, 0, proj, SvgRender::new().metrics());
+        svg.append_text_path(
+            &polyline, 
+            "ROAD", 
+            &PolylineProps::new("green", 3, 10), 
+            &TextProps::new("blue", 12, 700, 10)
+        );
+        svg.place_labels();
+        let data = String::from_utf8(svg.dump()).unwrap();
+        assert_eq!(data.matches("<textPath").count(), 1);
+
+        let proj = Proj::default();
+        let mut svg = SvgTile::new(0, 0, 0, proj, SvgRender::new().metrics());
+        svg.append_text_path(
+            &polyline, 
+            "ROAD", 
+            &PolylineProps::new("green", 3, 10), 
+            &TextProps { repeat_distance: Stops::new(80.0), ..TextProps::new("blue", 12, 700, 10) }
+        );
+        svg.place_labels();
+        let data = String::from_utf8(svg.dump()).unwrap();
+        assert_eq!(data.matches("<textPath").count(), 3);
+    }
+
+    #[test]
+    fn test_svg_point() {
+        let proj = Proj::default();
+        let mut svg = SvgTile::new(0, 0, 0, proj, SvgRender::new().metrics());
+        let point = Point { x: 0.0, y: 0.0 };
+        svg.append_point(&point, &PointProps::new(Marker::Circle, 8, "red", "white", 1, 10));
+        let square = PointProps { rotation: Stops::new(45.0), ..PointProps::new(Marker::Square, 8, "red", "white", 1, 10) };
+        svg.append_point(&point, &square);
+        svg.append_point(&Point { x: 0.0, y: 89.0 }, &square);
+        svg.append_point_text(&point, "POI", &square, &TextProps::new("blue", 12, 400, 10));
+        svg.append_point_text(&point, "POI", &square, &TextProps::new("blue", 12, 400, 10));
+        svg.place_labels();
+
+        let data = String::from_utf8(svg.dump()).unwrap();
+        assert!(data.contains(r#"cx="128""#));
+        assert!(data.contains(r#"r="4""#));
+        assert!(data.contains(r#"transform="rotate(45 128 128)""#));
+        assert_eq!(data.matches("<rect").count(), 1);
+        assert_eq!(data.matches("<text").count(), 2);
+    }
+
+    #[test]
+    fn test_svg_point_obstacle() {
+        // A marker right of the point takes the place of its label, which moves to the left
+        let proj = Proj::default();
+        let mut svg = SvgTile::new(0, 0, 0, proj, SvgRender::new().metrics());
+        let props = PointProps::new(Marker::Circle, 8, "red", "white", 1, 10);
+        let (x, y) = (proj.x_to_longitude(128.0, 0.0), proj.y_to_latitude(128.0, 0.0));
+        svg.append_point(&Point { x, y }, &props);
+        svg.append_point(&Point { x: proj.x_to_longitude(140.0, 0.0), y }, &props);
+        svg.append_point_text(&Point { x, y }, "POI", &props, &TextProps::new("blue", 12, 400, 10));
+        svg.place_labels();
+
+        let data = String::from_utf8(svg.dump()).unwrap();
+        let text = &data[data.find("<text").unwrap()..];
+        let left: f64 = text.split(" x=\"").nth(1).unwrap().split('"').next().unwrap().parse().unwrap();
+        assert!(left < 128.0);
+    }
+
+    #[test]
+    fn test_marker_icon() {
+        let file = std::env::temp_dir().join("tiny-gis-server-icon.svg");
+        std::fs::write(&file, "<svg/>").unwrap();
+        let marker = Marker::icon(file.to_str().unwrap()).unwrap();
+        match marker {
+            Marker::Icon(href) => assert_eq!(href, "data:image/svg+xml;base64,PHN2Zy8+"),
+            _ => panic!("not an icon"),
+        }
+        assert!(Marker::icon("not-exists.png").is_err());
+    }
+
+    #[test]
+    fn test_svg_labels() {
+        let proj = Proj::default();
+        let mut svg = SvgTile::new(0, 0, 0, proj, SvgRender::new().metrics());
+        for _ in 0..4 {
+            svg.append_text(
+                &Point { x: 0.0, y: 0.0 }, 
+                "hello world", 
+                &TextProps::new("red", 32, 700, 10)
+            );
+        }
+        svg.place_labels();
+
+        let data = String::from_utf8(svg.dump()).unwrap();
+        assert_eq!(data.matches("<text").count(), 3);
+    }
+
+    #[test]
+    fn test_svg_dash() {
+        let proj = Proj::default();
+        let mut svg = SvgTile::new(13398, 6724, 14, proj, SvgRender::new().metrics());
+        let mut polyline = Polyline::new();
+        polyline.append(&Point { x: 114.395, y: 30.67 });
+        polyline.append(&Point { x: 114.405, y: 30.67 });
+        svg.append_polyline(&polyline, &PolylineProps {
+            dash: vec![4.0, 2.5],
+            ..PolylineProps::new("black", 1, 10)
+        });
+
+        let data = String::from_utf8(svg.dump()).unwrap();
+        assert!(data.contains("stroke-dasharray:4,2.5"));
+    }
+
+    #[test]
+    fn test_svg_casing() {
+        let proj = Proj::default();
+        let mut svg = SvgTile::new(13398, 6724, 14, proj, SvgRender::new().metrics());
+        let mut polyline = Polyline::new();
+        polyline.append(&Point { x: 114.395, y: 30.67 });
+        polyline.append(&Point { x: 114.405, y: 30.67 });
+        svg.append_polyline(&polyline, &PolylineProps {
+            width: Stops::linear(vec![(12.0, 2.0), (16.0, 6.0)]),
+            cap: String::from("round"),
+            casing_color: String::from("grey"),
+            casing_width: Stops::new(1.5),
+            casing_priority: 5,
+            ..PolylineProps::new("white", 1, 10)
+        });
+        svg.append_polyline(&polyline, &PolylineProps::new("black", 1, 8));
+        svg.sort_tags();
+
+        let data = String::from_utf8(svg.dump()).unwrap();
+        let casing = data.find("stroke:grey;stroke-width:7;stroke-linecap:round").unwrap();
+        let line = data.find("stroke:white;stroke-width:4;stroke-linecap:round").unwrap();
+        let other = data.find("stroke:black;stroke-width:1;stroke-linecap:butt;stroke-linejoin:miter").unwrap();
+        assert!(casing < other && other < line);
+
+        // Without a casing priority the casing is just under its line
+        assert_eq!(PolylineProps::new("white", 1, 10).casing_priority, 9);
+    }
+
+    #[test]
+    fn test_svg_pattern() {
+        let proj = Proj::default();
+        let render = SvgRender::new();
+        let mut svg = SvgTile::new(13398, 6724, 14, proj, render.metrics());
+        let mut polygon = Polygon::new();
+        polygon.append(&Point { x: 100.0, y: 20.0 });
+        polygon.append(&Point { x: 120.0, y: 20.0 });
+        polygon.append(&Point { x: 120.0, y: 40.0 });
+        polygon.append(&Point { x: 100.0, y: 40.0 });
+        let props = PolygonProps {
+            fill_opacity: Stops::new(0.5),
+            border_dash: vec![8.0, 4.0],
+            pattern: Some(Pattern::new(PatternKind::Cross, "red", 8.0, 1.0)),
+            ..PolygonProps::new("green", "cyan", 3, 5, 10)
+        };
+        svg.append_polygon(&polygon, &props);
+        svg.append_polygon(&polygon, &props);
+        svg.sort_tags();
+
+        let data = String::from_utf8(svg.dump()).unwrap();
+        assert_eq!(data.matches("<pattern").count(), 1);
+        assert!(data.find("<defs>").unwrap() < data.find("<path").unwrap());
+        assert!(data.contains("fill:url(#ID_1);stroke:green;fill-opacity:0.5"));
+        assert!(data.contains("stroke-dasharray:8,4"));
+        assert_eq!(data.matches("<line").count(), 2);
+        assert!(render.to_png(&svg.dump()).is_some());
+    }
+
+    #[test]
+    fn test_svg_stops() {
+        let proj = Proj::default();
+        let mut svg = SvgTile::new(13398, 6724, 14, proj, SvgRender::new().metrics());
+        let props = TextProps {
+            size: Stops::linear(vec![(10.0, 10.0), (18.0, 26.0)]),
+            opacity: Stops::linear(vec![(12.0, 0.0), (16.0, 1.0)]),
+            ..TextProps::new("black", 0, 400, 10)
+        };
+        assert_eq!(svg.at(&props.size), 18.0);
+        svg.append_text(&Point { x: 114.40, y: 30.67 }, "text", &props);
+        svg.place_labels();
+
+        let data = String::from_utf8(svg.dump()).unwrap();
+        assert!(data.contains(r#"font-size="18""#));
+        assert!(data.contains(r#"opacity="0.5""#));
+    }
+
+    #[test]
+    fn test_svg_background() {
+        let proj = Proj::default();
+        let render = SvgRender::new();
+        let mut svg = SvgTile::metatile(13396, 6724, 14, 2, proj, render.metrics());
+        let mut polygon = Polygon::new();
+        polygon.append(&Point { x: 100.0, y: 20.0 });
+        polygon.append(&Point { x: 120.0, y: 20.0 });
+        polygon.append(&Point { x: 120.0, y: 40.0 });
+        polygon.append(&Point { x: 100.0, y: 40.0 });
+        svg.append_polygon(&polygon, &PolygonProps::new("green", "cyan", 3, i32::MIN + 1, i32::MIN + 1));
+        svg.append_background(&BackgroundProps {
+            pattern: Some(Pattern::new(PatternKind::Hatch, "grey", 8.0, 1.0)),
+            ..BackgroundProps::new("white")
+        });
+        svg.sort_tags();
+
+        let data = String::from_utf8(svg.dump()).unwrap();
+        // The pattern has a <rect> of its own in the <defs>, the full size one is the background
+        let (background, _) = data.match_indices("<rect")
+            .find(|(i, _)| {
+                let tag = &data[*i..*i + data[*i..].find('>').unwrap()];
+                tag.contains(r#"width="512""#) && tag.contains(r#"height="512""#) && tag.contains(r#"fill="url(#ID_1)""#)
+            })
+            .unwrap();
+        assert!(background < data.find("<path").unwrap());
+        assert!(render.to_pngs(&svg.dump(), 2).is_some());
+    }
+
+    #[test]
+    fn test_svg_polygon_holes() {
+        let proj = Proj::default();
+        let mut svg = SvgTile::new(0, 0, 0, proj, SvgRender::new().metrics());
+        let ring = |min: f64, max: f64| {
+            let mut polygon = Polygon::new();
+            for (x, y) in [(min, min), (max, min), (max, max), (min, max)] {
+                polygon.append(&Point { x: proj.x_to_longitude(x, 0.0), y: proj.y_to_latitude(y, 0.0) });
+            }
+            polygon
+        };
+        let mut polygon = ring(64.0, 192.0);
+        polygon.add_hole(ring(96.0, 160.0));
+        svg.append_polygon(&polygon, &PolygonProps::new("green", "cyan", 1, 5, 10));
+
+        let data = String::from_utf8(svg.dump()).unwrap();
+        assert!(data.contains(r#"fill-rule="evenodd""#));
+        assert_eq!(data.matches('M').count(), 2);
+        assert!(data.contains("Z M96,96 160,96 160,160 96,160Z"));
+        assert_eq!(data.matches("<polyline").count(), 2);
+    }
+
+    #[test]
+    fn test_svg_debug() {
+        let proj = Proj::default();
+        let render = SvgRender::new();
+        let mut svg = SvgTile::metatile(13396, 6724, 14, 2, proj, render.metrics());
+        svg.append_debug();
+        svg.sort_tags();
+
+        let data = String::from_utf8(svg.dump()).unwrap();
+        for text in ["14/13396/6724", "14/13397/6724", "14/13396/6725", "14/13397/6725"] {
+            assert!(data.contains(&format!(">{}</text>", text)));
+        }
+        assert!(data.contains(">114.38°</text>"));
+        assert!(data.contains(">30.66°</text>"));
+        assert!(render.to_pngs(&svg.dump(), 2).is_some());
+
+        // The geographic graticule goes up to the poles
+        let mut svg = SvgTile::new(0, 0, 0, Proj::Geographic, render.metrics());
+        svg.append_debug();
+        let data = String::from_utf8(svg.dump()).unwrap();
+        assert!(data.contains(">90°</text>"));
+        assert!(data.contains(">-90°</text>"));
+    }
+
+    #[test]
+    fn test_graticule_lines() {
+        let lines = graticule_lines(-180.0, 180.0, 90.0, 256.0, |longitude| (longitude + 180.0) / 360.0 * 256.0);
+        assert_eq!(lines, vec![(-180.0, 0.0), (-90.0, 64.0), (0.0, 128.0), (90.0, 192.0), (180.0, 256.0)]);
+
+        // Decreasing projections, like latitudes to y, are walked from the bottom of the canvas
+        let lines = graticule_lines(-90.0, 90.0, 0.1, 256.0, |latitude| (30.0 - latitude) * 1000.0);
+        let latitudes: Vec<f64> = lines.iter().map(|(latitude, _)| (latitude * 10.0).round() / 10.0).collect();
+        assert_eq!(latitudes, vec![29.8, 29.9, 30.0]);
+    }
+
+    #[test]
+    fn test_svg_clip() {
+        let proj = Proj::default();
+        let mut svg = SvgTile::new(13398, 6724, 14, proj, SvgRender::new().metrics());
+        let mut polygon = Polygon::new();
+        polygon.append(&Point { x: 100.0, y: 20.0 });
+        polygon.append(&Point { x: 120.0, y: 20.0 });
+        polygon.append(&Point { x: 120.0, y: 40.0 });
+        polygon.append(&Point { x: 100.0, y: 40.0 });
+        svg.append_polygon(
+            &polygon,
+            &PolygonProps::new("green", "cyan", 3, 5, 10)
+        );
+
+        let data = String::from_utf8(svg.dump()).unwrap();
+        assert!(data.contains(r#"points="-16,-16 -16,272 272,272 272,-16""#));
+    }
+
     #[test]
     fn text_svg_render() {
         let svg = r#"